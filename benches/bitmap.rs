@@ -62,6 +62,16 @@ mod dense_tests {
         });
     }
 
+    #[bench]
+    fn bench_ewah_bitmap_get(b: &mut Bencher) {
+        let bitmap = EwahBitmap::from(bitmap().as_str());
+        b.iter(|| {
+            for i in 0..bitmap.size {
+                bitmap.get(i);
+            }
+        });
+    }
+
     #[bench]
     fn bench_bitmap_set(b: &mut Bencher) {
         let mut bitmap = Bitmap::from(bitmap().as_str());
@@ -96,6 +106,13 @@ mod dense_tests {
         b.iter(|| &first & &second);
     }
 
+    #[bench]
+    fn bench_ewah_bitmap_and(b: &mut Bencher) {
+        let first = EwahBitmap::from(bitmap().as_str());
+        let second = EwahBitmap::from(another_bitmap().as_str());
+        b.iter(|| &first & &second);
+    }
+
     #[bench]
     fn bench_bitmap_or(b: &mut Bencher) {
         let first = Bitmap::from(bitmap().as_str());
@@ -110,6 +127,13 @@ mod dense_tests {
         b.iter(|| &first | &second);
     }
 
+    #[bench]
+    fn bench_ewah_bitmap_or(b: &mut Bencher) {
+        let first = EwahBitmap::from(bitmap().as_str());
+        let second = EwahBitmap::from(another_bitmap().as_str());
+        b.iter(|| &first | &second);
+    }
+
     #[bench]
     fn bench_bitmap_not(b: &mut Bencher) {
         let bitmap = Bitmap::from(DENSE_BITMAP);
@@ -122,6 +146,12 @@ mod dense_tests {
         b.iter(|| !&bitmap);
     }
 
+    #[bench]
+    fn bench_ewah_bitmap_not(b: &mut Bencher) {
+        let bitmap = EwahBitmap::from(bitmap().as_str());
+        b.iter(|| !&bitmap);
+    }
+
     #[bench]
     fn bench_bitmap_xor(b: &mut Bencher) {
         let first = Bitmap::from(bitmap().as_str());
@@ -135,6 +165,13 @@ mod dense_tests {
         let second = SparseBitmap::from(another_bitmap().as_str());
         b.iter(|| &first ^ &second);
     }
+
+    #[bench]
+    fn bench_ewah_bitmap_xor(b: &mut Bencher) {
+        let first = EwahBitmap::from(bitmap().as_str());
+        let second = EwahBitmap::from(another_bitmap().as_str());
+        b.iter(|| &first ^ &second);
+    }
 }
 
 #[cfg(test)]
@@ -171,6 +208,16 @@ mod sparse_tests {
         });
     }
 
+    #[bench]
+    fn bench_ewah_bitmap_get(b: &mut Bencher) {
+        let bitmap = EwahBitmap::from(bitmap().as_str());
+        b.iter(|| {
+            for i in 0..bitmap.size {
+                bitmap.get(i);
+            }
+        });
+    }
+
     #[bench]
     fn bench_bitmap_set(b: &mut Bencher) {
         let mut bitmap = Bitmap::from(bitmap().as_str());
@@ -205,6 +252,13 @@ mod sparse_tests {
         b.iter(|| &first & &second);
     }
 
+    #[bench]
+    fn bench_ewah_bitmap_and(b: &mut Bencher) {
+        let first = EwahBitmap::from(bitmap().as_str());
+        let second = EwahBitmap::from(another_bitmap().as_str());
+        b.iter(|| &first & &second);
+    }
+
     #[bench]
     fn bench_bitmap_or(b: &mut Bencher) {
         let first = Bitmap::from(bitmap().as_str());
@@ -219,6 +273,13 @@ mod sparse_tests {
         b.iter(|| &first | &second);
     }
 
+    #[bench]
+    fn bench_ewah_bitmap_or(b: &mut Bencher) {
+        let first = EwahBitmap::from(bitmap().as_str());
+        let second = EwahBitmap::from(another_bitmap().as_str());
+        b.iter(|| &first | &second);
+    }
+
     #[bench]
     fn bench_bitmap_not(b: &mut Bencher) {
         let bitmap = Bitmap::from(bitmap().as_str());
@@ -231,6 +292,12 @@ mod sparse_tests {
         b.iter(|| !&bitmap);
     }
 
+    #[bench]
+    fn bench_ewah_bitmap_not(b: &mut Bencher) {
+        let bitmap = EwahBitmap::from(bitmap().as_str());
+        b.iter(|| !&bitmap);
+    }
+
     #[bench]
     fn bench_bitmap_xor(b: &mut Bencher) {
         let first = Bitmap::from(bitmap().as_str());
@@ -244,4 +311,104 @@ mod sparse_tests {
         let second = SparseBitmap::from(another_bitmap().as_str());
         b.iter(|| &first ^ &second);
     }
+
+    #[bench]
+    fn bench_ewah_bitmap_xor(b: &mut Bencher) {
+        let first = EwahBitmap::from(bitmap().as_str());
+        let second = EwahBitmap::from(another_bitmap().as_str());
+        b.iter(|| &first ^ &second);
+    }
+}
+
+#[cfg(test)]
+mod run_count_tests {
+
+    use crate::*;
+    use test::Bencher;
+
+    /// Sparse bitmap with the given amount of runs of a single 1
+    fn bitmap(runs: usize) -> SparseBitmap {
+        SparseBitmap::from("01".repeat(runs).as_str())
+    }
+
+    /// Reads 1000 positions spread over the whole bitmap
+    fn bench_get(b: &mut Bencher, runs: usize) {
+        let bitmap = bitmap(runs);
+        let step = bitmap.size / 1000;
+        b.iter(|| {
+            for i in (0..bitmap.size).step_by(step) {
+                test::black_box(bitmap.get(i));
+            }
+        });
+    }
+
+    /// Splits and merges back a run in the middle of the bitmap. Finding the run is
+    /// logarithmic, while inserting and removing runs still moves the ones after it.
+    fn bench_set(b: &mut Bencher, runs: usize) {
+        let mut bitmap = bitmap(runs);
+        let position = runs / 2 * 2 + 1;
+        b.iter(|| {
+            bitmap.set(position - 1, true);
+            bitmap.set(position, false);
+            bitmap.set(position, true);
+            bitmap.set(position - 1, false);
+        });
+    }
+
+    #[bench]
+    fn bench_sparse_bitmap_get_1k_runs(b: &mut Bencher) {
+        bench_get(b, 1_000);
+    }
+
+    #[bench]
+    fn bench_sparse_bitmap_get_10k_runs(b: &mut Bencher) {
+        bench_get(b, 10_000);
+    }
+
+    #[bench]
+    fn bench_sparse_bitmap_get_100k_runs(b: &mut Bencher) {
+        bench_get(b, 100_000);
+    }
+
+    #[bench]
+    fn bench_sparse_bitmap_set_1k_runs(b: &mut Bencher) {
+        bench_set(b, 1_000);
+    }
+
+    #[bench]
+    fn bench_sparse_bitmap_set_10k_runs(b: &mut Bencher) {
+        bench_set(b, 10_000);
+    }
+
+    #[bench]
+    fn bench_sparse_bitmap_set_100k_runs(b: &mut Bencher) {
+        bench_set(b, 100_000);
+    }
+
+    /// Same as `bench_set`, over runs stored in a tree so that no run is moved
+    fn bench_tree_set(b: &mut Bencher, runs: usize) {
+        let mut bitmap = TreeSparseBitmap::from(&bitmap(runs));
+        let position = runs / 2 * 2 + 1;
+        b.iter(|| {
+            bitmap.set(position - 1, true);
+            bitmap.set(position, false);
+            bitmap.set(position, true);
+            bitmap.set(position - 1, false);
+        });
+    }
+
+    #[bench]
+    fn bench_tree_sparse_bitmap_set_1k_runs(b: &mut Bencher) {
+        bench_tree_set(b, 1_000);
+    }
+
+    #[bench]
+    fn bench_tree_sparse_bitmap_set_10k_runs(b: &mut Bencher) {
+        bench_tree_set(b, 10_000);
+    }
+
+    #[bench]
+    fn bench_tree_sparse_bitmap_set_100k_runs(b: &mut Bencher) {
+        bench_tree_set(b, 100_000);
+    }
 }
@@ -0,0 +1,40 @@
+#![no_main]
+
+use bitmap::{BitGrid, Bitmap, BitmapRef, BloomFilter};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(bitmap) = Bitmap::from_bytes(data) {
+        bitmap.assert_invariants();
+        assert_eq!(
+            Bitmap::from_bytes(&bitmap.to_bytes()).as_ref(),
+            Some(&bitmap)
+        );
+
+        let view = BitmapRef::from_bytes(data).expect("The view accepts the same bytes");
+        assert_eq!(Bitmap::from(&view), bitmap);
+        assert_eq!(view.count_ones(), bitmap.count_ones());
+    }
+
+    if let Some(filter) = BloomFilter::from_bytes(data) {
+        filter.bitmap().assert_invariants();
+        assert_eq!(
+            BloomFilter::from_bytes(&filter.to_bytes()).as_ref(),
+            Some(&filter)
+        );
+    }
+
+    if let Some(grid) = BitGrid::from_pbm(data) {
+        grid.bitmap().assert_invariants();
+        assert_eq!(grid.bitmap().size, grid.width * grid.height);
+        assert_eq!(BitGrid::from_pbm(&grid.to_pbm_raw()).as_ref(), Some(&grid));
+    }
+
+    if let Some(grid) = std::str::from_utf8(data).ok().and_then(BitGrid::from_xbm) {
+        grid.bitmap().assert_invariants();
+        assert_eq!(
+            BitGrid::from_xbm(&grid.to_xbm("grid")).as_ref(),
+            Some(&grid)
+        );
+    }
+});
@@ -0,0 +1,31 @@
+#![no_main]
+
+use bitmap::{Bitmap, SparseBitmap};
+use libfuzzer_sys::fuzz_target;
+
+fn bitmap(bits: &[bool]) -> Bitmap {
+    let mut bitmap = Bitmap::new(bits.len());
+    for (position, value) in bits.iter().enumerate() {
+        bitmap.set(position, *value);
+    }
+    bitmap
+}
+
+fuzz_target!(|input: (Vec<bool>, Vec<bool>)| {
+    let (first, second) = (bitmap(&input.0), bitmap(&input.1));
+    let (first_sparse, second_sparse) = (SparseBitmap::from(&first), SparseBitmap::from(&second));
+
+    let results = [
+        (&first & &second, &first_sparse & &second_sparse),
+        (&first | &second, &first_sparse | &second_sparse),
+        (&first ^ &second, &first_sparse ^ &second_sparse),
+        (!&first, !&first_sparse),
+    ];
+
+    for (bitmap, sparse) in results {
+        bitmap.assert_invariants();
+        assert_eq!(sparse.validate(), Ok(()));
+
+        assert_eq!(Bitmap::from(&sparse), bitmap);
+    }
+});
@@ -0,0 +1,27 @@
+#![no_main]
+
+use bitmap::{Bitmap, EwahBitmap, RoaringBitmap, SparseBitmap};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Characters other than 0 and 1 panic by design
+    let Ok(string) = std::str::from_utf8(data) else {
+        return;
+    };
+    if !string.chars().all(|char| char == '0' || char == '1') {
+        return;
+    }
+
+    let bitmap = Bitmap::from(string);
+    let sparse = SparseBitmap::from(string);
+
+    bitmap.assert_invariants();
+    assert_eq!(sparse.validate(), Ok(()));
+
+    assert_eq!(bitmap.size, string.len());
+    assert_eq!(sparse.to_string(), string);
+    assert_eq!(Bitmap::from(&sparse), bitmap);
+    assert_eq!(SparseBitmap::from(&bitmap), sparse);
+    assert_eq!(Bitmap::from(&RoaringBitmap::from(string)), bitmap);
+    assert_eq!(Bitmap::from(&EwahBitmap::from(string)), bitmap);
+});
@@ -0,0 +1,28 @@
+#![no_main]
+
+use bitmap::{Bitmap, SparseBitmap};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u16, Vec<(u16, bool)>)| {
+    let (size, operations) = input;
+    let size = size as usize;
+
+    let mut sparse = SparseBitmap::new(size);
+    let mut bitmap = Bitmap::new(size);
+
+    if size == 0 {
+        return;
+    }
+
+    for (position, value) in operations {
+        let position = position as usize % size;
+
+        sparse.set(position, value);
+        bitmap.set(position, value);
+
+        assert_eq!(sparse.validate(), Ok(()));
+        assert_eq!(sparse.get(position), value);
+    }
+
+    assert_eq!(Bitmap::from(&sparse), bitmap);
+});
@@ -0,0 +1,352 @@
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{bit_index, chunks_count, low_bits_mask, Bitmap};
+
+/// Amount of counters the modifications in progress are spread across, so that
+/// writers of different chunks do not contend on the same counter.
+const STRIPES: usize = 16;
+
+/// AtomicBitmap is a fixed size bitmap that can be modified from many threads at once.
+///
+/// Every bit is modified with a single atomic operation over its chunk, so writers
+/// never wait for each other. Writers do wait while a `snapshot` copies the chunks,
+/// which lets the snapshot see all bits at a single point in time. The bitmap is then
+/// not lock-free: a thread stalled in the middle of a snapshot blocks every writer
+/// until it resumes.
+#[derive(Debug)]
+pub struct AtomicBitmap {
+    chunks: Vec<AtomicUsize>,
+    writers: Vec<Stripe>,
+    snapshots: AtomicUsize,
+    pub size: usize,
+}
+
+impl AtomicBitmap {
+    /// Create a new `AtomicBitmap` with a fixed size.
+    pub fn new(size: usize) -> Self {
+        let chunks = (0..chunks_count(size, usize::BITS as usize))
+            .map(|_| AtomicUsize::new(0))
+            .collect();
+
+        AtomicBitmap::with_chunks(chunks, size)
+    }
+
+    /// Get the bit value from a given position
+    #[inline(always)]
+    pub fn get(&self, position: usize) -> bool {
+        let (chunk_index, bit_index_in_chunk) = bit_index(position, usize::BITS as usize);
+
+        self.chunks[chunk_index].load(Ordering::Acquire) & (1 << bit_index_in_chunk) != 0
+    }
+
+    /// Set a 1 in a given position
+    #[inline(always)]
+    pub fn set(&self, position: usize) {
+        self.test_and_set(position);
+    }
+
+    /// Set a 0 in a given position
+    #[inline(always)]
+    pub fn clear(&self, position: usize) {
+        let (chunk_index, bit_index_in_chunk) = self.bit_index(position);
+
+        self.write(chunk_index, |chunk| {
+            chunk.fetch_and(!(1 << bit_index_in_chunk), Ordering::AcqRel)
+        });
+    }
+
+    /// Set a 1 in a given position, returning the previous bit value. Among many
+    /// threads setting the same position, only one of them sees a 0.
+    #[inline(always)]
+    pub fn test_and_set(&self, position: usize) -> bool {
+        let (chunk_index, bit_index_in_chunk) = self.bit_index(position);
+        let position_bit = 1 << bit_index_in_chunk;
+
+        let previous = self.write(chunk_index, |chunk| {
+            chunk.fetch_or(position_bit, Ordering::AcqRel)
+        });
+
+        previous & position_bit != 0
+    }
+
+    /// Apply a bitwise OR between the chunk at the given index and `chunk`, returning
+    /// the previous chunk. Bits beyond the bitmap size are ignored.
+    #[inline(always)]
+    pub fn fetch_or(&self, chunk_index: usize, chunk: usize) -> usize {
+        if chunk_index >= self.chunks.len() {
+            panic!("Index out of bounds");
+        }
+
+        let chunk = chunk & low_bits_mask(self.size - chunk_index * usize::BITS as usize);
+
+        self.write(chunk_index, |atomic| {
+            atomic.fetch_or(chunk, Ordering::AcqRel)
+        })
+    }
+
+    /// Copy the bitmap into a `Bitmap` that shows the state of all bits at a single
+    /// point in time.
+    ///
+    /// New modifications wait until the copy is done, and the copy starts once the
+    /// modifications already in progress are finished. Taking snapshots back to back
+    /// from many threads may then keep writers waiting.
+    pub fn snapshot(&self) -> Bitmap {
+        let mut bitmap = Bitmap::new(self.size);
+
+        self.snapshots.fetch_add(1, Ordering::SeqCst);
+        for stripe in &self.writers {
+            while stripe.0.load(Ordering::SeqCst) != 0 {
+                core::hint::spin_loop();
+            }
+        }
+
+        for (chunk, atomic) in bitmap.chunks.iter_mut().zip(&self.chunks) {
+            *chunk = atomic.load(Ordering::Acquire);
+        }
+        self.snapshots.fetch_sub(1, Ordering::SeqCst);
+
+        bitmap
+    }
+
+    /// Convert the bitmap into a `Bitmap`, once no other thread can access it.
+    pub fn into_bitmap(self) -> Bitmap {
+        Bitmap {
+            chunks: self
+                .chunks
+                .into_iter()
+                .map(AtomicUsize::into_inner)
+                .collect(),
+            size: self.size,
+        }
+    }
+
+    fn with_chunks(chunks: Vec<AtomicUsize>, size: usize) -> Self {
+        let stripes = STRIPES.min(chunks.len()).max(1);
+
+        AtomicBitmap {
+            chunks,
+            writers: (0..stripes).map(|_| Stripe(AtomicUsize::new(0))).collect(),
+            snapshots: AtomicUsize::new(0),
+            size,
+        }
+    }
+
+    #[inline(always)]
+    fn bit_index(&self, position: usize) -> (usize, usize) {
+        if position >= self.size {
+            panic!("Index out of bounds");
+        }
+
+        bit_index(position, usize::BITS as usize)
+    }
+
+    /// Run a modification of the chunk at the given index, counting it as in progress
+    /// in the stripe of the chunk. Modifications announced while a snapshot is being
+    /// taken step back and wait for it to finish.
+    #[inline(always)]
+    fn write<F: FnOnce(&AtomicUsize) -> usize>(
+        &self,
+        chunk_index: usize,
+        modification: F,
+    ) -> usize {
+        let stripe = &self.writers[chunk_index % self.writers.len()].0;
+
+        loop {
+            stripe.fetch_add(1, Ordering::SeqCst);
+            if self.snapshots.load(Ordering::SeqCst) == 0 {
+                break;
+            }
+
+            stripe.fetch_sub(1, Ordering::SeqCst);
+            while self.snapshots.load(Ordering::Relaxed) != 0 {
+                core::hint::spin_loop();
+            }
+        }
+
+        let result = modification(&self.chunks[chunk_index]);
+        stripe.fetch_sub(1, Ordering::SeqCst);
+
+        result
+    }
+}
+
+/// Stripe counts the modifications in progress over a subset of the chunks. Every
+/// stripe takes a whole cache line, so that counting in one of them does not slow
+/// down the writers of the others.
+#[derive(Debug)]
+#[repr(align(64))]
+struct Stripe(AtomicUsize);
+
+impl From<&Bitmap> for AtomicBitmap {
+    fn from(bitmap: &Bitmap) -> Self {
+        let chunks = (0..bitmap.chunks.len())
+            .map(|index| AtomicUsize::new(bitmap.masked_chunk(index, bitmap.chunks[index])))
+            .collect();
+
+        AtomicBitmap::with_chunks(chunks, bitmap.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_set_clear() {
+        let bitmap = AtomicBitmap::new(100);
+
+        bitmap.set(3);
+        bitmap.set(70);
+        assert!(bitmap.get(3));
+        assert!(bitmap.get(70));
+
+        bitmap.clear(3);
+        assert!(!bitmap.get(3));
+
+        assert!(!bitmap.test_and_set(5));
+        assert!(bitmap.test_and_set(5));
+
+        assert_eq!(bitmap.fetch_or(1, usize::MAX), 1 << 6);
+        assert_eq!(bitmap.snapshot().count_ones(), 1 + 36);
+        assert_eq!(bitmap.into_bitmap().count_ones(), 1 + 36);
+    }
+
+    #[test]
+    fn test_atomic_from_bitmap() {
+        let bitmap = !&Bitmap::from("00100");
+        let atomic = AtomicBitmap::from(&bitmap);
+
+        assert_eq!(atomic.snapshot(), Bitmap::from("11011"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_atomic_set_out_of_bounds() {
+        AtomicBitmap::new(10).set(10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_atomic_fetch_or_out_of_bounds() {
+        AtomicBitmap::new(64).fetch_or(1, 1);
+    }
+
+    /// Tests that modify the bitmap from many threads
+    #[cfg(feature = "std")]
+    mod threads {
+        use super::*;
+        use core::sync::atomic::AtomicBool;
+        use std::thread;
+
+        const THREADS: usize = 8;
+
+        #[test]
+        fn test_atomic_concurrent_set() {
+            let bitmap = AtomicBitmap::new(10_000);
+
+            thread::scope(|scope| {
+                for thread in 0..THREADS {
+                    let bitmap = &bitmap;
+                    scope.spawn(move || {
+                        for position in (thread..bitmap.size).step_by(THREADS) {
+                            bitmap.set(position);
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(bitmap.snapshot().count_ones(), 10_000);
+        }
+
+        #[test]
+        fn test_atomic_test_and_set_claims_once() {
+            let bitmap = AtomicBitmap::new(10_000);
+            let claimed = AtomicUsize::new(0);
+
+            thread::scope(|scope| {
+                for _ in 0..THREADS {
+                    scope.spawn(|| {
+                        for position in 0..bitmap.size {
+                            if !bitmap.test_and_set(position) {
+                                claimed.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(claimed.into_inner(), 10_000);
+        }
+
+        #[test]
+        fn test_atomic_concurrent_set_and_clear() {
+            let bitmap = AtomicBitmap::new(1_000);
+
+            // Every thread owns the positions of its remainder, and finishes setting them
+            thread::scope(|scope| {
+                for thread in 0..THREADS {
+                    let bitmap = &bitmap;
+                    scope.spawn(move || {
+                        for _ in 0..100 {
+                            for position in (thread..bitmap.size).step_by(THREADS) {
+                                bitmap.set(position);
+                                bitmap.clear(position);
+                            }
+                        }
+                        for position in (thread..bitmap.size).step_by(THREADS) {
+                            bitmap.set(position);
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(bitmap.snapshot().count_ones(), 1_000);
+        }
+
+        /// Whether the 1s of the bitmap inside `range` are all next to each other.
+        fn contiguous(bitmap: &Bitmap, range: core::ops::Range<usize>) -> bool {
+            let ones: Vec<usize> = bitmap.iter_ones().filter(|p| range.contains(p)).collect();
+            ones.windows(2).all(|pair| pair[1] == pair[0] + 1)
+        }
+
+        /// Stops the writers of a test once dropped, even if an assertion fails before.
+        struct StopOnDrop<'a>(&'a AtomicBool);
+
+        impl Drop for StopOnDrop<'_> {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::Relaxed);
+            }
+        }
+
+        #[test]
+        fn test_atomic_snapshot_is_consistent() {
+            let bitmap = AtomicBitmap::new(1 << 14);
+            let half = bitmap.size / 2;
+            let stop = AtomicBool::new(false);
+
+            thread::scope(|scope| {
+                // Every writer sets and then clears its half in order, so the 1s of each
+                // half are always next to each other. A snapshot mixing chunks from
+                // different points in time would show a gap.
+                for range in [0..half, half..bitmap.size] {
+                    let (bitmap, stop) = (&bitmap, &stop);
+                    scope.spawn(move || {
+                        while !stop.load(Ordering::Relaxed) {
+                            range.clone().for_each(|position| bitmap.set(position));
+                            range.clone().for_each(|position| bitmap.clear(position));
+                        }
+                    });
+                }
+
+                let _stop = StopOnDrop(&stop);
+                for _ in 0..200 {
+                    let snapshot = bitmap.snapshot();
+
+                    assert!(contiguous(&snapshot, 0..half));
+                    assert!(contiguous(&snapshot, half..bitmap.size));
+                }
+            });
+        }
+    }
+}
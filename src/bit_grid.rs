@@ -0,0 +1,487 @@
+use alloc::{format, string::String, vec, vec::Vec};
+
+use crate::Bitmap;
+
+/// BitGrid is a two-dimensional raster of bits, such as a mask or an occupancy grid.
+///
+/// The bits are stored in a `Bitmap` row after row, so the position `(x, y)` is the bit
+/// `y * width + x`. It reads and writes the PBM (plain `P1` and raw `P4`) and XBM
+/// image formats, where a 1 is a black pixel.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BitGrid {
+    bits: Bitmap,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl BitGrid {
+    /// Creates a new `BitGrid` with all bits set to 0
+    pub fn new(width: usize, height: usize) -> Self {
+        let Some(size) = width.checked_mul(height) else {
+            panic!("Grid size overflows usize");
+        };
+
+        BitGrid {
+            bits: Bitmap::new(size),
+            width,
+            height,
+        }
+    }
+
+    /// Get the bit value at the given coordinates
+    #[inline(always)]
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.bits.get(self.position(x, y))
+    }
+
+    /// Set a bit value at the given coordinates
+    #[inline(always)]
+    pub fn set(&mut self, x: usize, y: usize, value: bool) {
+        let position = self.position(x, y);
+        self.bits.set(position, value);
+    }
+
+    /// Bits of the grid, row after row
+    pub fn bitmap(&self) -> &Bitmap {
+        &self.bits
+    }
+
+    /// Returns a copy of the given row, with a bit per column
+    pub fn row(&self, y: usize) -> Bitmap {
+        let mut row = Bitmap::new(self.width);
+
+        for x in 0..self.width {
+            row.set(x, self.get(x, y));
+        }
+
+        row
+    }
+
+    /// Count the amount of 1s in the grid
+    pub fn count_ones(&self) -> usize {
+        self.bits.count_ones()
+    }
+
+    /// Returns the smallest rectangle holding every 1 of the grid as
+    /// `(min_x, min_y, max_x, max_y)`, all of them included, or `None` if there is
+    /// no 1 at all.
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        let mut ones = self.bits.iter_ones();
+        let first = ones.next()?;
+
+        let (mut min_x, min_y) = (first % self.width, first / self.width);
+        let (mut max_x, mut max_y) = (min_x, min_y);
+
+        for position in ones {
+            let (x, y) = (position % self.width, position / self.width);
+
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            max_y = y;
+        }
+
+        Some((min_x, min_y, max_x, max_y))
+    }
+
+    /// Sets `value` to the region of bits connected to `(x, y)` that share its value,
+    /// moving horizontally and vertically. Returns the amount of changed bits.
+    pub fn flood_fill(&mut self, x: usize, y: usize, value: bool) -> usize {
+        let target = self.get(x, y);
+        if target == value {
+            return 0;
+        }
+
+        let mut pending = vec![(x, y)];
+        let mut filled = 0;
+
+        while let Some((x, y)) = pending.pop() {
+            if self.get(x, y) != target {
+                continue;
+            }
+
+            self.set(x, y, value);
+            filled += 1;
+
+            if x > 0 {
+                pending.push((x - 1, y));
+            }
+            if x + 1 < self.width {
+                pending.push((x + 1, y));
+            }
+            if y > 0 {
+                pending.push((x, y - 1));
+            }
+            if y + 1 < self.height {
+                pending.push((x, y + 1));
+            }
+        }
+
+        filled
+    }
+
+    /// Count the 1s among the (up to 8) neighbors of `(x, y)`, without the bit itself
+    pub fn neighbors(&self, x: usize, y: usize) -> usize {
+        let (x_range, y_range) = (
+            x.saturating_sub(1)..(x + 2).min(self.width),
+            y.saturating_sub(1)..(y + 2).min(self.height),
+        );
+
+        let ones = y_range
+            .flat_map(|ny| x_range.clone().map(move |nx| (nx, ny)))
+            .filter(|(nx, ny)| self.get(*nx, *ny))
+            .count();
+
+        ones - self.get(x, y) as usize
+    }
+
+    /// Returns the next generation of Conway's Game of Life, where the bits beyond the
+    /// borders are always 0: a 1 survives with 2 or 3 neighbors, and a 0 becomes a 1
+    /// with exactly 3 neighbors.
+    pub fn step_life(&self) -> BitGrid {
+        let mut next = BitGrid::new(self.width, self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let alive = matches!((self.get(x, y), self.neighbors(x, y)), (true, 2) | (_, 3));
+                next.set(x, y, alive);
+            }
+        }
+
+        next
+    }
+
+    /// Writes the grid in the plain PBM format (`P1`), a digit per bit
+    pub fn to_pbm_plain(&self) -> String {
+        let mut pbm = format!("P1\n{} {}\n", self.width, self.height);
+
+        for y in 0..self.height {
+            let row: Vec<&str> = (0..self.width)
+                .map(|x| if self.get(x, y) { "1" } else { "0" })
+                .collect();
+
+            pbm.push_str(&row.join(" "));
+            pbm.push('\n');
+        }
+
+        pbm
+    }
+
+    /// Writes the grid in the raw PBM format (`P4`), where every row is packed in
+    /// bytes with the first column in the most significant bit.
+    pub fn to_pbm_raw(&self) -> Vec<u8> {
+        let mut pbm = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+
+        for y in 0..self.height {
+            pbm.extend(self.row_bytes(y, |bit| 0x80 >> bit));
+        }
+
+        pbm
+    }
+
+    /// Reads a grid in the plain (`P1`) or raw (`P4`) PBM format, or returns `None` if
+    /// the content is not a valid PBM image.
+    pub fn from_pbm(bytes: &[u8]) -> Option<BitGrid> {
+        let mut header = Header { bytes, position: 0 };
+
+        let magic = header.token()?;
+        let width: usize = core::str::from_utf8(header.token()?).ok()?.parse().ok()?;
+        let height: usize = core::str::from_utf8(header.token()?).ok()?.parse().ok()?;
+
+        // Every bit takes at least a digit or an eighth of a byte, which bounds the
+        // allocation before reading them
+        let data = &bytes[header.position..];
+        let row_bytes = width.div_ceil(8);
+        let required = match magic {
+            b"P1" => width.checked_mul(height)?,
+            _ => row_bytes.checked_mul(height)?.checked_add(1)?,
+        };
+        if data.len() < required {
+            return None;
+        }
+
+        let mut grid = BitGrid::new(width, height);
+
+        match magic {
+            b"P1" => {
+                let mut digits = data.iter().filter(|byte| !byte.is_ascii_whitespace());
+
+                for position in 0..width * height {
+                    match digits.next()? {
+                        b'0' => {}
+                        b'1' => grid.bits.set(position, true),
+                        _ => return None,
+                    }
+                }
+            }
+            b"P4" => {
+                // A single whitespace separates the header from the packed rows
+                let data = &data[1..];
+
+                // Iterating over the positions skips the rows of an image with no columns
+                for position in 0..width * height {
+                    let (x, y) = (position % width, position / width);
+                    let byte = data[y * row_bytes + x / 8];
+                    grid.bits.set(position, byte & (0x80 >> (x % 8)) != 0);
+                }
+            }
+            _ => return None,
+        }
+
+        Some(grid)
+    }
+
+    /// Writes the grid in the XBM format, as C source with the given name. Every row is
+    /// packed in bytes with the first column in the least significant bit.
+    pub fn to_xbm(&self, name: &str) -> String {
+        let bytes: Vec<String> = (0..self.height)
+            .flat_map(|y| self.row_bytes(y, |bit| 1 << bit))
+            .map(|byte| format!("0x{byte:02x}"))
+            .collect();
+
+        let lines: Vec<String> = bytes.chunks(12).map(|line| line.join(", ")).collect();
+
+        format!(
+            "#define {name}_width {}\n#define {name}_height {}\nstatic unsigned char {name}_bits[] = {{\n   {} }};\n",
+            self.width,
+            self.height,
+            lines.join(",\n   ")
+        )
+    }
+
+    /// Reads a grid in the XBM format, or returns `None` if the content is not a valid
+    /// XBM image.
+    pub fn from_xbm(xbm: &str) -> Option<BitGrid> {
+        let define = |suffix: &str| -> Option<usize> {
+            xbm.lines()
+                .filter_map(|line| line.trim().strip_prefix("#define "))
+                .find_map(|define| {
+                    let (name, value) = define.split_once(char::is_whitespace)?;
+                    name.ends_with(suffix).then(|| value.trim().parse().ok())?
+                })
+        };
+
+        let (width, height) = (define("_width")?, define("_height")?);
+
+        let (_, data) = xbm.split_once('{')?;
+        let (data, _) = data.split_once('}')?;
+        let bytes = data
+            .split(',')
+            .map(str::trim)
+            .filter(|byte| !byte.is_empty())
+            .map(|byte| u8::from_str_radix(byte.strip_prefix("0x")?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+
+        let row_bytes = width.div_ceil(8);
+        if Some(bytes.len()) != row_bytes.checked_mul(height) {
+            return None;
+        }
+
+        let mut grid = BitGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                grid.set(x, y, bytes[y * row_bytes + x / 8] & (1 << (x % 8)) != 0);
+            }
+        }
+
+        Some(grid)
+    }
+
+    /// Packs a row in bytes of 8 columns, using `mask` to place the column `x % 8`
+    /// inside its byte. The last byte is padded with 0s.
+    fn row_bytes(&self, y: usize, mask: fn(usize) -> u8) -> Vec<u8> {
+        let mut bytes = vec![0; self.width.div_ceil(8)];
+
+        for x in 0..self.width {
+            if self.get(x, y) {
+                bytes[x / 8] |= mask(x % 8);
+            }
+        }
+
+        bytes
+    }
+
+    #[inline(always)]
+    fn position(&self, x: usize, y: usize) -> usize {
+        if x >= self.width || y >= self.height {
+            panic!("Index out of bounds");
+        }
+
+        y * self.width + x
+    }
+}
+
+/// Header reads the whitespace separated tokens of a PBM header, skipping comments.
+struct Header<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Header<'a> {
+    fn token(&mut self) -> Option<&'a [u8]> {
+        loop {
+            match self.bytes.get(self.position)? {
+                b'#' => {
+                    while self
+                        .bytes
+                        .get(self.position)
+                        .is_some_and(|byte| *byte != b'\n')
+                    {
+                        self.position += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => self.position += 1,
+                _ => break,
+            }
+        }
+
+        let start = self.position;
+        while self
+            .bytes
+            .get(self.position)
+            .is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            self.position += 1;
+        }
+
+        Some(&self.bytes[start..self.position])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Creates a grid from rows of '.' and '#'
+    fn grid(rows: &[&str]) -> BitGrid {
+        let mut grid = BitGrid::new(rows[0].len(), rows.len());
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, char) in row.chars().enumerate() {
+                grid.set(x, y, char == '#');
+            }
+        }
+
+        grid
+    }
+
+    #[test]
+    fn test_grid_get_set_row() {
+        let grid = grid(&["#..#", ".##.", "...."]);
+
+        assert!(grid.get(3, 0));
+        assert!(!grid.get(0, 1));
+        assert_eq!(grid.row(1), Bitmap::from("0110"));
+        assert_eq!(grid.count_ones(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_grid_out_of_bounds() {
+        BitGrid::new(4, 3).get(4, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Grid size overflows usize")]
+    fn test_grid_size_overflow() {
+        BitGrid::new(usize::MAX / 2, 3);
+    }
+
+    #[test]
+    fn test_grid_bounding_box() {
+        assert_eq!(
+            grid(&["....", "..#.", ".#..", "...."]).bounding_box(),
+            Some((1, 1, 2, 2))
+        );
+        assert_eq!(BitGrid::new(3, 3).bounding_box(), None);
+    }
+
+    #[test]
+    fn test_grid_flood_fill() {
+        let mut grid = grid(&["..#..", "..#..", "###..", "....."]);
+
+        // The top-left corner is closed by the wall
+        assert_eq!(grid.flood_fill(0, 0, true), 4);
+        assert_eq!(grid, self::grid(&["###..", "###..", "###..", "....."]));
+        assert_eq!(grid.flood_fill(0, 0, true), 0);
+        assert_eq!(grid.flood_fill(4, 3, true), 11);
+    }
+
+    #[test]
+    fn test_grid_life() {
+        let blinker = grid(&[".....", "..#..", "..#..", "..#..", "....."]);
+
+        assert_eq!(blinker.neighbors(2, 2), 2);
+        assert_eq!(blinker.neighbors(1, 2), 3);
+        assert_eq!(
+            blinker.step_life(),
+            grid(&[".....", ".....", ".###.", ".....", "....."])
+        );
+        assert_eq!(blinker.step_life().step_life(), blinker);
+    }
+
+    #[test]
+    fn test_grid_pbm() {
+        let grid = grid(&["#.........", ".#......##"]);
+
+        assert_eq!(
+            grid.to_pbm_plain(),
+            "P1\n10 2\n1 0 0 0 0 0 0 0 0 0\n0 1 0 0 0 0 0 0 1 1\n"
+        );
+        assert_eq!(
+            grid.to_pbm_raw(),
+            [b"P4\n10 2\n".as_slice(), &[0x80, 0x00, 0x40, 0xC0]].concat()
+        );
+
+        assert_eq!(
+            BitGrid::from_pbm(grid.to_pbm_plain().as_bytes()),
+            Some(grid.clone())
+        );
+        assert_eq!(BitGrid::from_pbm(&grid.to_pbm_raw()), Some(grid.clone()));
+        assert_eq!(
+            BitGrid::from_pbm(b"P1\n# a comment\n3 1\n101"),
+            Some(self::grid(&["#.#"]))
+        );
+        assert_eq!(BitGrid::from_pbm(b"P1\n3 1\n10"), None);
+        assert_eq!(BitGrid::from_pbm(b"P2\n3 1\n101"), None);
+        assert_eq!(BitGrid::from_pbm(b"P4\n99999999999 99999999999\n"), None);
+
+        // An image with no columns has no bits to read, whatever its height
+        let empty = BitGrid::from_pbm(b"P4\n0 18446744073709551615\n").unwrap();
+        assert_eq!((empty.width, empty.count_ones()), (0, 0));
+    }
+
+    #[test]
+    fn test_grid_xbm() {
+        let grid = grid(&["#.........", ".#......##"]);
+        let xbm = grid.to_xbm("mask");
+
+        assert_eq!(
+            xbm,
+            "#define mask_width 10\n#define mask_height 2\nstatic unsigned char mask_bits[] = {\n   0x01, 0x00, 0x02, 0x03 };\n"
+        );
+        assert_eq!(BitGrid::from_xbm(&xbm), Some(grid));
+        assert_eq!(BitGrid::from_xbm("#define mask_width 10\n{ 0x01 }"), None);
+    }
+
+    fn grid_of_size() -> impl Strategy<Value = BitGrid> {
+        (1..40usize, 1..40usize).prop_flat_map(|(width, height)| {
+            prop::collection::vec(any::<bool>(), width * height).prop_map(move |bits| {
+                let mut grid = BitGrid::new(width, height);
+                for (position, value) in bits.into_iter().enumerate() {
+                    grid.set(position % width, position / width, value);
+                }
+                grid
+            })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_grid_formats_round_trip(grid in grid_of_size()) {
+            prop_assert_eq!(BitGrid::from_pbm(grid.to_pbm_plain().as_bytes()), Some(grid.clone()));
+            prop_assert_eq!(BitGrid::from_pbm(&grid.to_pbm_raw()), Some(grid.clone()));
+            prop_assert_eq!(BitGrid::from_xbm(&grid.to_xbm("grid")), Some(grid));
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{bit_index, chunks_count, Bitmap};
+
+mod gf2;
+
+/// BitMatrix is a two-dimensional matrix of bits, such as a relation between two sets.
+///
+/// Every row is stored as the chunks of a `Bitmap` with a bit per column, and all rows
+/// are kept one after the other in a single vector of chunks. Operations between rows
+/// then work on whole chunks, and the position `(row, column)` is the bit `column` of
+/// the row.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BitMatrix {
+    chunks: Vec<usize>,
+    row_chunks: usize,
+    pub rows: usize,
+    pub columns: usize,
+}
+
+impl BitMatrix {
+    /// Creates a new `BitMatrix` with all bits set to 0
+    pub fn new(rows: usize, columns: usize) -> Self {
+        let row_chunks = chunks_count(columns, usize::BITS as usize);
+
+        BitMatrix {
+            chunks: vec![0; rows * row_chunks],
+            row_chunks,
+            rows,
+            columns,
+        }
+    }
+
+    /// Creates a new square `BitMatrix` with 1s in the diagonal
+    pub fn identity(size: usize) -> Self {
+        let mut matrix = BitMatrix::new(size, size);
+
+        for position in 0..size {
+            matrix.set(position, position, true);
+        }
+
+        matrix
+    }
+
+    /// Get the bit value from a given row and column
+    #[inline(always)]
+    pub fn get(&self, row: usize, column: usize) -> bool {
+        let (chunk, bit) = self.bit_index(row, column);
+        self.chunks[chunk] & (1 << bit) != 0
+    }
+
+    /// Set a bit value in a given row and column
+    #[inline(always)]
+    pub fn set(&mut self, row: usize, column: usize, value: bool) {
+        let (chunk, bit) = self.bit_index(row, column);
+
+        if value {
+            self.chunks[chunk] |= 1 << bit;
+        } else {
+            self.chunks[chunk] &= !(1 << bit);
+        }
+    }
+
+    /// Returns a copy of the given row, with a bit per column
+    pub fn row(&self, row: usize) -> Bitmap {
+        Bitmap {
+            chunks: self.row_chunks(row).to_vec(),
+            size: self.columns,
+        }
+    }
+
+    /// Returns a copy of the given column, with a bit per row
+    pub fn column(&self, column: usize) -> Bitmap {
+        let mut bitmap = Bitmap::new(self.rows);
+
+        for row in 0..self.rows {
+            bitmap.set(row, self.get(row, column));
+        }
+
+        bitmap
+    }
+
+    /// Replaces the given row with the bits of `bitmap`
+    pub fn set_row(&mut self, row: usize, bitmap: &Bitmap) {
+        self.check_row_size(bitmap);
+
+        for (index, chunk) in self.row_chunks_mut(row).iter_mut().enumerate() {
+            *chunk = bitmap.masked_chunk(index, bitmap.chunks[index]);
+        }
+    }
+
+    /// Applies a bitwise AND between every row and `bitmap`
+    pub fn and_rows(&mut self, bitmap: &Bitmap) {
+        self.check_row_size(bitmap);
+
+        for row in 0..self.rows {
+            for (chunk, rhs) in self.row_chunks_mut(row).iter_mut().zip(&bitmap.chunks) {
+                *chunk &= rhs;
+            }
+        }
+    }
+
+    /// Applies a bitwise OR between every row and `bitmap`
+    pub fn or_rows(&mut self, bitmap: &Bitmap) {
+        self.check_row_size(bitmap);
+
+        for row in 0..self.rows {
+            for (index, chunk) in self.row_chunks_mut(row).iter_mut().enumerate() {
+                *chunk |= bitmap.masked_chunk(index, bitmap.chunks[index]);
+            }
+        }
+    }
+
+    /// Returns the transposed matrix, where the rows become the columns.
+    ///
+    /// The matrix is transposed in square blocks of `usize::BITS` rows and columns,
+    /// each of them being a chunk per row. Every block is transposed in place by
+    /// swapping its halves, then the halves of the halves, down to single bits, and
+    /// moved to the mirrored block of the result.
+    pub fn transpose(&self) -> BitMatrix {
+        let chunk_bit_size = usize::BITS as usize;
+        let mut transposed = BitMatrix::new(self.columns, self.rows);
+        let mut block = vec![0; chunk_bit_size];
+
+        for block_row in 0..chunks_count(self.rows, chunk_bit_size) {
+            for block_column in 0..self.row_chunks {
+                for (index, chunk) in block.iter_mut().enumerate() {
+                    let row = block_row * chunk_bit_size + index;
+                    *chunk = if row < self.rows {
+                        self.chunks[row * self.row_chunks + block_column]
+                    } else {
+                        0
+                    };
+                }
+
+                transpose_block(&mut block);
+
+                for (index, chunk) in block.iter().enumerate() {
+                    let row = block_column * chunk_bit_size + index;
+                    if row < transposed.rows {
+                        transposed.chunks[row * transposed.row_chunks + block_row] = *chunk;
+                    }
+                }
+            }
+        }
+
+        transposed
+    }
+
+    /// Returns the boolean product with `rhs`: the position `(i, j)` is 1 when there
+    /// is some `k` with `(i, k)` set in this matrix and `(k, j)` set in `rhs`.
+    ///
+    /// Every row of the result is the OR of the rows of `rhs` selected by the 1s of
+    /// the same row of this matrix.
+    pub fn multiply(&self, rhs: &BitMatrix) -> BitMatrix {
+        if self.columns != rhs.rows {
+            panic!("Can not multiply matrices with mismatching sizes");
+        }
+
+        let mut product = BitMatrix::new(self.rows, rhs.columns);
+
+        for row in 0..self.rows {
+            for column in self.row(row).iter_ones() {
+                let (target, source) = (product.row_chunks_mut(row), rhs.row_chunks(column));
+
+                for (chunk, rhs) in target.iter_mut().zip(source) {
+                    *chunk |= rhs;
+                }
+            }
+        }
+
+        product
+    }
+
+    /// Returns the transitive closure of a square matrix, following Warshall's
+    /// algorithm: `(i, j)` is 1 when `j` can be reached from `i` through 1s.
+    ///
+    /// For every intermediate `k`, the rows that reach `k` also reach every column
+    /// reached by the row `k`.
+    pub fn transitive_closure(&self) -> BitMatrix {
+        if self.rows != self.columns {
+            panic!("Can not calculate the transitive closure of a non-square matrix");
+        }
+
+        let mut closure = self.clone();
+        let mut through = vec![0; self.row_chunks];
+
+        for k in 0..self.rows {
+            through.copy_from_slice(closure.row_chunks(k));
+
+            for row in 0..self.rows {
+                if closure.get(row, k) {
+                    for (chunk, rhs) in closure.row_chunks_mut(row).iter_mut().zip(&through) {
+                        *chunk |= rhs;
+                    }
+                }
+            }
+        }
+
+        closure
+    }
+
+    #[inline(always)]
+    fn bit_index(&self, row: usize, column: usize) -> (usize, usize) {
+        if row >= self.rows || column >= self.columns {
+            panic!("Index out of bounds");
+        }
+
+        let (chunk, bit) = bit_index(column, usize::BITS as usize);
+        (row * self.row_chunks + chunk, bit)
+    }
+
+    fn row_chunks(&self, row: usize) -> &[usize] {
+        &self.chunks[row * self.row_chunks..(row + 1) * self.row_chunks]
+    }
+
+    fn row_chunks_mut(&mut self, row: usize) -> &mut [usize] {
+        &mut self.chunks[row * self.row_chunks..(row + 1) * self.row_chunks]
+    }
+
+    fn check_row_size(&self, bitmap: &Bitmap) {
+        if bitmap.size != self.columns {
+            panic!("Bitmap size does not match the matrix columns");
+        }
+    }
+}
+
+/// Transposes a square block of bits in place, where the bit `j` of the chunk `i` is
+/// the position `(i, j)`.
+///
+/// Starting with halves of `usize::BITS / 2` bits, the upper half of the chunk `i`
+/// swaps places with the lower half of the chunk `i + half`. Repeating it with halves
+/// of half the size moves every bit to its mirrored position.
+fn transpose_block(block: &mut [usize]) {
+    let mut width = usize::BITS as usize / 2;
+    // 1s in the lower half of every group of `2 * width` bits
+    let mut mask = usize::MAX >> width;
+
+    while width > 0 {
+        for index in 0..block.len() {
+            if index & width == 0 {
+                let swap = ((block[index] >> width) ^ block[index + width]) & mask;
+                block[index] ^= swap << width;
+                block[index + width] ^= swap;
+            }
+        }
+
+        width /= 2;
+        mask ^= mask << width;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::matrix;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_matrix_rows_and_columns() {
+        let mut matrix = matrix(&["1001", "0110", "0011"]);
+
+        assert!(matrix.get(0, 3));
+        assert!(!matrix.get(2, 0));
+        assert_eq!(matrix.row(1), Bitmap::from("0110"));
+        assert_eq!(matrix.column(3), Bitmap::from("101"));
+
+        matrix.set_row(2, &Bitmap::from("1000"));
+        assert_eq!(matrix.row(2), Bitmap::from("1000"));
+    }
+
+    #[test]
+    fn test_matrix_row_operations() {
+        let mut matrix = matrix(&["1001", "0110"]);
+
+        matrix.and_rows(&Bitmap::from("0011"));
+        assert_eq!(matrix, self::matrix(&["1000", "0100"]));
+
+        matrix.or_rows(&!&Bitmap::from("0111"));
+        assert_eq!(matrix, self::matrix(&["1001", "0101"]));
+    }
+
+    #[test]
+    fn test_matrix_without_columns() {
+        let mut matrix = BitMatrix::new(3, 0);
+
+        matrix.or_rows(&Bitmap::new(0));
+        assert_eq!(matrix.transpose(), BitMatrix::new(0, 3));
+        assert_eq!(matrix.row(1), Bitmap::new(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Bitmap size does not match")]
+    fn test_matrix_row_operation_with_different_size() {
+        BitMatrix::new(2, 4).and_rows(&Bitmap::new(5));
+    }
+
+    #[test]
+    fn test_matrix_transpose() {
+        let matrix = matrix(&["1001", "0110", "0011"]);
+
+        assert_eq!(
+            matrix.transpose(),
+            self::matrix(&["100", "010", "011", "101"])
+        );
+    }
+
+    #[test]
+    fn test_matrix_multiply() {
+        let lhs = matrix(&["10", "01", "11"]);
+        let rhs = matrix(&["011", "100"]);
+
+        assert_eq!(lhs.multiply(&rhs), matrix(&["011", "100", "111"]));
+        assert_eq!(
+            lhs.multiply(&BitMatrix::identity(2)),
+            matrix(&["10", "01", "11"])
+        );
+    }
+
+    #[test]
+    fn test_matrix_transitive_closure() {
+        // 0 -> 1 -> 2 -> 3, and 4 alone
+        let graph = matrix(&["01000", "00100", "00010", "00000", "00000"]);
+
+        assert_eq!(
+            graph.transitive_closure(),
+            matrix(&["01110", "00110", "00010", "00000", "00000"])
+        );
+    }
+
+    fn bits(rows: usize, columns: usize) -> impl Strategy<Value = BitMatrix> {
+        prop::collection::vec(any::<bool>(), rows * columns).prop_map(move |bits| {
+            let mut matrix = BitMatrix::new(rows, columns);
+            for (position, value) in bits.into_iter().enumerate() {
+                matrix.set(position / columns, position % columns, value);
+            }
+            matrix
+        })
+    }
+
+    fn matrix_of_size() -> impl Strategy<Value = BitMatrix> {
+        (1..150usize, 1..150usize).prop_flat_map(|(rows, columns)| bits(rows, columns))
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_matrix_transpose_mirrors_bits(matrix in matrix_of_size()) {
+            let transposed = matrix.transpose();
+
+            for row in 0..matrix.rows {
+                for column in 0..matrix.columns {
+                    prop_assert_eq!(transposed.get(column, row), matrix.get(row, column));
+                }
+            }
+            prop_assert_eq!(transposed.transpose(), matrix);
+        }
+
+        #[test]
+        fn test_matrix_multiply_matches_definition(
+            (lhs, rhs) in (1..80usize, 1..80usize, 1..80usize)
+                .prop_flat_map(|(rows, inner, columns)| (bits(rows, inner), bits(inner, columns))),
+        ) {
+            let product = lhs.multiply(&rhs);
+
+            for row in 0..lhs.rows {
+                for column in 0..rhs.columns {
+                    let expected = (0..lhs.columns).any(|k| lhs.get(row, k) && rhs.get(k, column));
+                    prop_assert_eq!(product.get(row, column), expected);
+                }
+            }
+        }
+
+        #[test]
+        fn test_matrix_closure_matches_repeated_products(
+            graph in (1..40usize).prop_flat_map(|size| bits(size, size)),
+        ) {
+            // Reachability in 1..=n steps, adding one step at a time
+            let mut reach = graph.clone();
+            for _ in 0..graph.rows {
+                let step = reach.multiply(&graph);
+                for row in 0..graph.rows {
+                    let row_bits = &reach.row(row) | &step.row(row);
+                    reach.set_row(row, &row_bits);
+                }
+            }
+
+            prop_assert_eq!(graph.transitive_closure(), reach);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use alloc::vec::Vec;
+
+use crate::{BitMatrix, Bitmap};
+
+/// Linear algebra over GF(2), the field of the bits where the addition is a XOR and
+/// the multiplication is an AND. Every row of a `BitMatrix` is a vector, and adding
+/// a row to another one is a XOR over their chunks.
+impl BitMatrix {
+    /// Returns the rank of the matrix: the amount of linearly independent rows
+    pub fn rank(&self) -> usize {
+        self.clone().eliminate().len()
+    }
+
+    /// Returns the reduced row echelon form of the matrix. Every non-zero row has a
+    /// leading 1 (the pivot) at the right of the pivot of the previous row, and the
+    /// column of every pivot has no other 1.
+    pub fn reduced_row_echelon(&self) -> BitMatrix {
+        let mut reduced = self.clone();
+        reduced.eliminate();
+        reduced
+    }
+
+    /// Solves `Ax = b`, returning one of the solutions or `None` if there is none.
+    /// The variables that do not appear as pivots are set to 0.
+    pub fn solve(&self, b: &Bitmap) -> Option<Bitmap> {
+        if b.size != self.rows {
+            panic!("Bitmap size does not match the matrix rows");
+        }
+
+        let mut rhs = BitMatrix::new(self.rows, 1);
+        for row in b.iter_ones() {
+            rhs.set(row, 0, true);
+        }
+
+        let mut augmented = self.augment(&rhs);
+        let pivots = augmented.eliminate();
+
+        // A pivot in `b` means a row `0 = 1`
+        if pivots.last() == Some(&self.columns) {
+            return None;
+        }
+
+        let mut solution = Bitmap::new(self.columns);
+        for (row, column) in pivots.into_iter().enumerate() {
+            solution.set(column, augmented.get(row, self.columns));
+        }
+
+        Some(solution)
+    }
+
+    /// Returns the inverse of a square matrix, or `None` if it is singular.
+    ///
+    /// Reducing `[A | I]` turns `A` into the identity when it can be inverted, which
+    /// leaves the inverse in place of `I`.
+    pub fn inverse(&self) -> Option<BitMatrix> {
+        if self.rows != self.columns {
+            panic!("Can not invert a non-square matrix");
+        }
+
+        let size = self.rows;
+        let mut augmented = self.augment(&BitMatrix::identity(size));
+
+        if augmented
+            .eliminate()
+            .iter()
+            .take_while(|column| **column < size)
+            .count()
+            < size
+        {
+            return None;
+        }
+
+        let mut inverse = BitMatrix::new(size, size);
+        for row in 0..size {
+            for column in 0..size {
+                inverse.set(row, column, augmented.get(row, size + column));
+            }
+        }
+
+        Some(inverse)
+    }
+
+    /// Returns a basis of the nullspace: the vectors `x` with `Ax = 0`.
+    ///
+    /// Every column without a pivot in the reduced form is a free variable, and gives
+    /// the vector with a 1 in that variable, and in every pivot variable whose row has
+    /// a 1 in the free column.
+    pub fn nullspace(&self) -> Vec<Bitmap> {
+        let mut reduced = self.clone();
+        let pivots = reduced.eliminate();
+
+        let mut is_pivot = Bitmap::new(self.columns);
+        for column in &pivots {
+            is_pivot.set(*column, true);
+        }
+
+        (0..self.columns)
+            .filter(|column| !is_pivot.get(*column))
+            .map(|free| {
+                let mut vector = Bitmap::new(self.columns);
+                vector.set(free, true);
+
+                for (row, column) in pivots.iter().enumerate() {
+                    vector.set(*column, reduced.get(row, free));
+                }
+
+                vector
+            })
+            .collect()
+    }
+
+    /// Returns the product `Ax` over GF(2): every bit is the parity of the 1s shared
+    /// by a row and `x`.
+    pub fn multiply_vector_gf2(&self, x: &Bitmap) -> Bitmap {
+        if x.size != self.columns {
+            panic!("Bitmap size does not match the matrix columns");
+        }
+
+        let mut product = Bitmap::new(self.rows);
+
+        for row in 0..self.rows {
+            let parity =
+                self.row_chunks(row)
+                    .iter()
+                    .enumerate()
+                    .fold(0, |parity, (index, chunk)| {
+                        parity ^ (chunk & x.masked_chunk(index, x.chunks[index])).count_ones()
+                    });
+
+            product.set(row, parity % 2 == 1);
+        }
+
+        product
+    }
+
+    /// Returns the product with `rhs` over GF(2). Unlike `multiply`, the rows of `rhs`
+    /// selected by a row of this matrix are added with a XOR.
+    pub fn multiply_gf2(&self, rhs: &BitMatrix) -> BitMatrix {
+        if self.columns != rhs.rows {
+            panic!("Can not multiply matrices with mismatching sizes");
+        }
+
+        let mut product = BitMatrix::new(self.rows, rhs.columns);
+
+        for row in 0..self.rows {
+            for column in self.row(row).iter_ones() {
+                let (target, source) = (product.row_chunks_mut(row), rhs.row_chunks(column));
+
+                for (chunk, rhs) in target.iter_mut().zip(source) {
+                    *chunk ^= rhs;
+                }
+            }
+        }
+
+        product
+    }
+
+    /// Turns the matrix into its reduced row echelon form with Gauss-Jordan
+    /// elimination, returning the pivot column of every non-zero row.
+    fn eliminate(&mut self) -> Vec<usize> {
+        let mut pivots = Vec::new();
+
+        for column in 0..self.columns {
+            let Some(pivot) = (pivots.len()..self.rows).find(|row| self.get(*row, column)) else {
+                continue;
+            };
+
+            let pivot_row = pivots.len();
+            self.swap_rows(pivot_row, pivot);
+
+            for row in 0..self.rows {
+                if row != pivot_row && self.get(row, column) {
+                    self.xor_rows(row, pivot_row);
+                }
+            }
+
+            pivots.push(column);
+        }
+
+        pivots
+    }
+
+    /// Returns the matrix with the columns of `rhs` after its own columns
+    fn augment(&self, rhs: &BitMatrix) -> BitMatrix {
+        let mut augmented = BitMatrix::new(self.rows, self.columns + rhs.columns);
+
+        for row in 0..self.rows {
+            augmented.row_chunks_mut(row)[..self.row_chunks].copy_from_slice(self.row_chunks(row));
+
+            for column in rhs.row(row).iter_ones() {
+                augmented.set(row, self.columns + column, true);
+            }
+        }
+
+        augmented
+    }
+
+    fn swap_rows(&mut self, first: usize, second: usize) {
+        if first != second {
+            for index in 0..self.row_chunks {
+                self.chunks.swap(
+                    first * self.row_chunks + index,
+                    second * self.row_chunks + index,
+                );
+            }
+        }
+    }
+
+    /// Adds the row `source` to the row `target`, with a XOR over their chunks.
+    fn xor_rows(&mut self, target: usize, source: usize) {
+        for index in 0..self.row_chunks {
+            self.chunks[target * self.row_chunks + index] ^=
+                self.chunks[source * self.row_chunks + index];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::matrix;
+    use alloc::vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_gf2_rank_and_echelon_form() {
+        // The third row is the sum of the first two
+        let matrix = matrix(&["1101", "0111", "1010"]);
+
+        assert_eq!(matrix.rank(), 2);
+        assert_eq!(
+            matrix.reduced_row_echelon(),
+            self::matrix(&["1010", "0111", "0000"])
+        );
+        assert_eq!(BitMatrix::identity(70).rank(), 70);
+        assert_eq!(BitMatrix::new(3, 3).rank(), 0);
+    }
+
+    #[test]
+    fn test_gf2_solve() {
+        let matrix = matrix(&["110", "011", "101"]);
+
+        let solution = matrix.solve(&Bitmap::from("110")).unwrap();
+        assert_eq!(matrix.multiply_vector_gf2(&solution), Bitmap::from("110"));
+
+        // Every row sums to 0 with the other two, so the parity of `b` must be even
+        assert_eq!(matrix.solve(&Bitmap::from("100")), None);
+    }
+
+    #[test]
+    fn test_gf2_inverse() {
+        let matrix = matrix(&["110", "010", "011"]);
+        let inverse = matrix.inverse().unwrap();
+
+        assert_eq!(matrix.multiply_gf2(&inverse), BitMatrix::identity(3));
+        assert_eq!(self::matrix(&["110", "011", "101"]).inverse(), None);
+    }
+
+    #[test]
+    fn test_gf2_nullspace() {
+        let matrix = matrix(&["1101", "0111", "1010"]);
+        let nullspace = matrix.nullspace();
+
+        assert_eq!(nullspace, vec![Bitmap::from("0111"), Bitmap::from("1010")]);
+        for vector in &nullspace {
+            assert_eq!(matrix.multiply_vector_gf2(vector).count_ones(), 0);
+        }
+    }
+
+    fn bits(rows: usize, columns: usize) -> impl Strategy<Value = BitMatrix> {
+        prop::collection::vec(any::<bool>(), rows * columns).prop_map(move |bits| {
+            let mut matrix = BitMatrix::new(rows, columns);
+            for (position, value) in bits.into_iter().enumerate() {
+                matrix.set(position / columns, position % columns, value);
+            }
+            matrix
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_gf2_rank_nullity(
+            matrix in (1..80usize, 1..80usize).prop_flat_map(|(rows, columns)| bits(rows, columns)),
+        ) {
+            let rank = matrix.rank();
+            let nullspace = matrix.nullspace();
+
+            prop_assert_eq!(rank, matrix.transpose().rank());
+            prop_assert_eq!(rank + nullspace.len(), matrix.columns);
+            for vector in &nullspace {
+                prop_assert_eq!(matrix.multiply_vector_gf2(vector).count_ones(), 0);
+            }
+        }
+
+        #[test]
+        fn test_gf2_solve_finds_a_solution(
+            (matrix, x) in (1..80usize, 1..80usize)
+                .prop_flat_map(|(rows, columns)| (bits(rows, columns), bits(1, columns))),
+        ) {
+            let b = matrix.multiply_vector_gf2(&x.row(0));
+            let solution = matrix.solve(&b).unwrap();
+
+            prop_assert_eq!(matrix.multiply_vector_gf2(&solution), b);
+        }
+
+        #[test]
+        fn test_gf2_inverse_of_invertible(matrix in (1..80usize).prop_flat_map(|size| bits(size, size))) {
+            match matrix.inverse() {
+                Some(inverse) => {
+                    prop_assert_eq!(matrix.multiply_gf2(&inverse), BitMatrix::identity(matrix.rows));
+                    prop_assert_eq!(inverse.multiply_gf2(&matrix), BitMatrix::identity(matrix.rows));
+                }
+                None => prop_assert!(matrix.rank() < matrix.rows),
+            }
+        }
+    }
+}
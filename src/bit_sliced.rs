@@ -0,0 +1,335 @@
+use alloc::vec::Vec;
+
+use crate::Bitmap;
+
+/// BitSlicedIndex stores an integer value per row as a list of `Bitmap` slices, where
+/// the slice `i` holds the bit `i` of the value of every row. Comparisons and
+/// aggregations are then answered with boolean operations over the slices, one per
+/// bit of the value, instead of looking at the values one by one.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BitSlicedIndex {
+    slices: Vec<Bitmap>,
+    exists: Bitmap,
+    pub size: usize,
+}
+
+impl BitSlicedIndex {
+    /// Creates a new `BitSlicedIndex` for a fixed amount of rows, none with a value
+    pub fn new(size: usize) -> Self {
+        BitSlicedIndex {
+            slices: Vec::new(),
+            exists: Bitmap::new(size),
+            size,
+        }
+    }
+
+    /// Get the value of a given row, if it has one
+    pub fn get(&self, row: usize) -> Option<u64> {
+        if !self.exists.get(row) {
+            return None;
+        }
+
+        let value = self
+            .slices
+            .iter()
+            .enumerate()
+            .filter(|(_, slice)| slice.get(row))
+            .fold(0, |value, (bit, _)| value | 1 << bit);
+
+        Some(value)
+    }
+
+    /// Set the value of a given row
+    pub fn set(&mut self, row: usize, value: u64) {
+        if row >= self.size {
+            panic!("Index out of bounds");
+        }
+
+        // Add the slices for the bits the current slices can not represent
+        let bits = (u64::BITS - value.leading_zeros()) as usize;
+        while self.slices.len() < bits {
+            self.slices.push(Bitmap::new(self.size));
+        }
+
+        for (bit, slice) in self.slices.iter_mut().enumerate() {
+            slice.set(row, value & (1 << bit) != 0);
+        }
+        self.exists.set(row, true);
+    }
+
+    /// Remove the value of a given row
+    pub fn clear(&mut self, row: usize) {
+        for slice in &mut self.slices {
+            slice.set(row, false);
+        }
+        self.exists.set(row, false);
+    }
+
+    /// Rows that have a value
+    pub fn exists(&self) -> &Bitmap {
+        &self.exists
+    }
+
+    /// Rows with a value equal to `value`
+    pub fn equal(&self, value: u64) -> Bitmap {
+        self.compare(value).equal
+    }
+
+    /// Rows with a value lower than `value`
+    pub fn less_than(&self, value: u64) -> Bitmap {
+        self.compare(value).less
+    }
+
+    /// Rows with a value lower than or equal to `value`
+    pub fn less_or_equal(&self, value: u64) -> Bitmap {
+        let comparison = self.compare(value);
+        &comparison.less | &comparison.equal
+    }
+
+    /// Rows with a value greater than `value`
+    pub fn greater_than(&self, value: u64) -> Bitmap {
+        self.compare(value).greater
+    }
+
+    /// Rows with a value greater than or equal to `value`
+    pub fn greater_or_equal(&self, value: u64) -> Bitmap {
+        let comparison = self.compare(value);
+        &comparison.greater | &comparison.equal
+    }
+
+    /// Rows with a value between `low` and `high`, both included
+    pub fn between(&self, low: u64, high: u64) -> Bitmap {
+        &self.greater_or_equal(low) & &self.less_or_equal(high)
+    }
+
+    /// Sum of the values of the rows in `filter`.
+    ///
+    /// Every slice contributes with the amount of filtered rows that have its bit set,
+    /// weighted by the bit position.
+    pub fn sum(&self, filter: &Bitmap) -> u128 {
+        let filter = filter & &self.exists;
+
+        self.slices
+            .iter()
+            .enumerate()
+            .map(|(bit, slice)| ((slice & &filter).count_ones() as u128) << bit)
+            .sum()
+    }
+
+    /// Minimum value of the rows in `filter`, or `None` if none of them has a value.
+    ///
+    /// Starting from the most significant slice, the candidates are narrowed down to
+    /// the rows with a 0 in the slice, unless no candidate has a 0 there.
+    pub fn min(&self, filter: &Bitmap) -> Option<u64> {
+        let mut candidates = filter & &self.exists;
+        if candidates.count_ones() == 0 {
+            return None;
+        }
+
+        let mut min = 0;
+        for (bit, slice) in self.slices.iter().enumerate().rev() {
+            let zeros = &candidates & &!slice;
+
+            if zeros.count_ones() > 0 {
+                candidates = zeros;
+            } else {
+                min |= 1 << bit;
+            }
+        }
+
+        Some(min)
+    }
+
+    /// Maximum value of the rows in `filter`, or `None` if none of them has a value.
+    ///
+    /// Starting from the most significant slice, the candidates are narrowed down to
+    /// the rows with a 1 in the slice, unless no candidate has a 1 there.
+    pub fn max(&self, filter: &Bitmap) -> Option<u64> {
+        let mut candidates = filter & &self.exists;
+        if candidates.count_ones() == 0 {
+            return None;
+        }
+
+        let mut max = 0;
+        for (bit, slice) in self.slices.iter().enumerate().rev() {
+            let ones = &candidates & slice;
+
+            if ones.count_ones() > 0 {
+                candidates = ones;
+                max |= 1 << bit;
+            }
+        }
+
+        Some(max)
+    }
+
+    /// Compares the value of every row against `value`, following O'Neil's range
+    /// evaluation algorithm.
+    ///
+    /// All rows with a value start as equal. Going from the most significant slice to
+    /// the least significant one, the rows that are still equal and differ from `value`
+    /// in the slice bit are moved to the lower or greater rows.
+    fn compare(&self, value: u64) -> Comparison {
+        let mut comparison = Comparison {
+            less: Bitmap::new(self.size),
+            equal: self.exists.clone(),
+            greater: Bitmap::new(self.size),
+        };
+
+        // Values with more bits than slices are greater than every row
+        if self.slices.len() < u64::BITS as usize && value >> self.slices.len() != 0 {
+            comparison.less = comparison.equal;
+            comparison.equal = Bitmap::new(self.size);
+            return comparison;
+        }
+
+        for (bit, slice) in self.slices.iter().enumerate().rev() {
+            if value & (1 << bit) != 0 {
+                comparison.less = &comparison.less | &(&comparison.equal & &!slice);
+                comparison.equal = &comparison.equal & slice;
+            } else {
+                comparison.greater = &comparison.greater | &(&comparison.equal & slice);
+                comparison.equal = &comparison.equal & &!slice;
+            }
+        }
+
+        comparison
+    }
+}
+
+impl From<&[u64]> for BitSlicedIndex {
+    fn from(values: &[u64]) -> Self {
+        let mut index = BitSlicedIndex::new(values.len());
+
+        for (row, value) in values.iter().enumerate() {
+            index.set(row, *value);
+        }
+
+        index
+    }
+}
+
+/// Comparison splits the rows with a value in the ones lower, equal or greater than
+/// the compared value.
+struct Comparison {
+    less: Bitmap,
+    equal: Bitmap,
+    greater: Bitmap,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use proptest::prelude::*;
+
+    fn rows(bitmap: &Bitmap) -> Vec<usize> {
+        bitmap.iter_ones().collect()
+    }
+
+    fn all(size: usize) -> Bitmap {
+        !&Bitmap::new(size)
+    }
+
+    #[test]
+    fn test_bit_sliced_get_set() {
+        let mut index = BitSlicedIndex::new(4);
+
+        index.set(0, 5);
+        index.set(2, 1000);
+        index.set(3, 0);
+
+        assert_eq!(index.get(0), Some(5));
+        assert_eq!(index.get(1), None);
+        assert_eq!(index.get(2), Some(1000));
+        assert_eq!(index.get(3), Some(0));
+        assert_eq!(index.slices.len(), 10);
+
+        index.set(2, 7);
+        index.clear(0);
+
+        assert_eq!(index.get(0), None);
+        assert_eq!(index.get(2), Some(7));
+    }
+
+    #[test]
+    fn test_bit_sliced_comparisons() {
+        let index = BitSlicedIndex::from([18, 35, 42, 18, 7, 64].as_slice());
+
+        assert_eq!(rows(&index.equal(18)), vec![0, 3]);
+        assert_eq!(rows(&index.less_than(18)), vec![4]);
+        assert_eq!(rows(&index.less_or_equal(18)), vec![0, 3, 4]);
+        assert_eq!(rows(&index.greater_than(42)), vec![5]);
+        assert_eq!(rows(&index.greater_or_equal(42)), vec![2, 5]);
+        assert_eq!(rows(&index.between(18, 42)), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_bit_sliced_compare_beyond_slices() {
+        let index = BitSlicedIndex::from([1, 2, 3].as_slice());
+
+        assert_eq!(rows(&index.less_than(1000)), vec![0, 1, 2]);
+        assert_eq!(rows(&index.equal(1000)), vec![]);
+        assert_eq!(rows(&index.greater_than(1000)), vec![]);
+        assert_eq!(rows(&index.less_than(u64::MAX)), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_bit_sliced_skips_missing_values() {
+        let mut index = BitSlicedIndex::new(3);
+        index.set(1, 4);
+
+        assert_eq!(rows(&index.less_than(10)), vec![1]);
+        assert_eq!(rows(&index.equal(0)), vec![]);
+        assert_eq!(index.sum(&all(3)), 4);
+        assert_eq!(index.min(&all(3)), Some(4));
+    }
+
+    #[test]
+    fn test_bit_sliced_aggregations() {
+        let index = BitSlicedIndex::from([18, 35, 42, 18, 7, 64].as_slice());
+        let filter = Bitmap::from("011110");
+
+        assert_eq!(index.sum(&all(6)), 184);
+        assert_eq!(index.sum(&filter), 102);
+        assert_eq!(index.min(&filter), Some(7));
+        assert_eq!(index.max(&filter), Some(42));
+        assert_eq!(index.min(&Bitmap::new(6)), None);
+        assert_eq!(index.max(&Bitmap::new(6)), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_bit_sliced_matches_values(
+            values in prop::collection::vec(prop::option::of(0..300u64), 1..100),
+            value in 0..300u64,
+            high in 0..300u64,
+        ) {
+            let mut index = BitSlicedIndex::new(values.len());
+            for (row, value) in values.iter().enumerate() {
+                if let Some(value) = value {
+                    index.set(row, *value);
+                }
+            }
+
+            let expected = |predicate: &dyn Fn(u64) -> bool| -> Vec<usize> {
+                values
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, row_value)| row_value.is_some_and(predicate))
+                    .map(|(row, _)| row)
+                    .collect()
+            };
+
+            prop_assert_eq!(rows(&index.equal(value)), expected(&|row| row == value));
+            prop_assert_eq!(rows(&index.less_than(value)), expected(&|row| row < value));
+            prop_assert_eq!(rows(&index.greater_than(value)), expected(&|row| row > value));
+            prop_assert_eq!(rows(&index.between(value, high)), expected(&|row| row >= value && row <= high));
+
+            let present = values.iter().flatten();
+            prop_assert_eq!(index.sum(&all(values.len())), present.clone().map(|value| *value as u128).sum());
+            prop_assert_eq!(index.min(&all(values.len())), present.clone().min().copied());
+            prop_assert_eq!(index.max(&all(values.len())), present.max().copied());
+        }
+    }
+}
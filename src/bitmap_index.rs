@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem::size_of;
+
+use crate::run_store::Store;
+use crate::{chunks_count, Bitmap, Run, SparseBitmap};
+
+/// Amount of runs a posting keeps before considering a dense bitmap. Below it, the
+/// runs take little memory no matter how many rows the column has.
+const SPARSE_MIN_RUNS: usize = 64;
+
+/// BitmapIndex is an equality-encoded index over a column of values: it stores, for
+/// every distinct value, the bitmap of the rows holding it.
+///
+/// Rows are identified by their position in the column. Removing a row keeps the
+/// positions of the following rows, and the removed row matches no lookup at all.
+#[derive(Debug, Clone)]
+pub struct BitmapIndex<T> {
+    postings: HashMap<T, Posting>,
+    nulls: Posting,
+    present: Bitmap,
+    rows: usize,
+}
+
+impl<T: Hash + Eq> BitmapIndex<T> {
+    /// Creates a new `BitmapIndex` without any row
+    pub fn new() -> Self {
+        BitmapIndex {
+            postings: HashMap::new(),
+            nulls: Posting::new(),
+            present: Bitmap::new(0),
+            rows: 0,
+        }
+    }
+
+    /// Amount of rows in the column, including the removed ones
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    /// Whether the column has no rows
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// Amount of distinct values in the column
+    pub fn cardinality(&self) -> usize {
+        self.postings.len()
+    }
+
+    /// Appends a row at the end of the column, and returns its position
+    pub fn push(&mut self, value: Option<T>) -> usize {
+        let row = self.rows;
+        self.rows += 1;
+
+        match value {
+            Some(value) => {
+                self.postings
+                    .entry(value)
+                    .or_insert_with(Posting::new)
+                    .push(row);
+
+                if self.present.size <= row {
+                    self.present.resize((row + 1).max(2 * self.present.size));
+                }
+                self.present.set(row, true);
+            }
+            None => self.nulls.push(row),
+        }
+
+        row
+    }
+
+    /// Removes the row at the given position from the index. Removing a row twice
+    /// has no effect.
+    ///
+    /// Finding the value of the row requires looking at the bitmap of every
+    /// distinct value, until the one holding the row.
+    pub fn remove(&mut self, row: usize) {
+        if row >= self.rows {
+            panic!("Index out of bounds");
+        }
+
+        if self.nulls.remove(row) {
+            return;
+        }
+
+        // Null and already removed rows are not present in any value
+        if row >= self.present.size || !self.present.get(row) {
+            return;
+        }
+        self.present.set(row, false);
+
+        let mut removed = false;
+        self.postings.retain(|_, posting| {
+            if removed || !posting.remove(row) {
+                return true;
+            }
+
+            removed = true;
+            !posting.is_empty()
+        });
+    }
+
+    /// Rows holding the given value
+    pub fn eq(&self, value: &T) -> Bitmap {
+        match self.postings.get(value) {
+            Some(posting) => posting.to_bitmap(self.rows),
+            None => Bitmap::new(self.rows),
+        }
+    }
+
+    /// Rows holding any of the given values
+    pub fn in_set<'a, I>(&self, values: I) -> Bitmap
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: 'a,
+    {
+        values
+            .into_iter()
+            .fold(Bitmap::new(self.rows), |rows, value| {
+                &rows | &self.eq(value)
+            })
+    }
+
+    /// Rows holding a value different from the given one. Null rows are not included,
+    /// as they are neither equal nor different to any value.
+    pub fn not_eq(&self, value: &T) -> Bitmap {
+        &self.is_not_null() ^ &self.eq(value)
+    }
+
+    /// Rows without a value
+    pub fn is_null(&self) -> Bitmap {
+        self.nulls.to_bitmap(self.rows)
+    }
+
+    /// Rows with any value
+    pub fn is_not_null(&self) -> Bitmap {
+        let mut present = self.present.clone();
+        present.resize(self.rows);
+        present
+    }
+}
+
+impl<T: Hash + Eq> Default for BitmapIndex<T> {
+    fn default() -> Self {
+        BitmapIndex::new()
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<Option<T>> for BitmapIndex<T> {
+    fn from_iter<I: IntoIterator<Item = Option<T>>>(iter: I) -> Self {
+        let mut index = BitmapIndex::new();
+
+        for value in iter {
+            index.push(value);
+        }
+
+        index
+    }
+}
+
+/// Posting stores the rows of a single value. Values start with a `SparseBitmap`, and
+/// move to a `Bitmap` once their runs take more memory than the dense chunks.
+#[derive(Debug, Clone)]
+enum Posting {
+    Sparse(SparseBitmap),
+    Dense(Bitmap),
+}
+
+impl Posting {
+    fn new() -> Posting {
+        Posting::Sparse(SparseBitmap::new(0))
+    }
+
+    /// Adds a row after every other row of the posting.
+    fn push(&mut self, row: usize) {
+        match self {
+            Posting::Sparse(sparse) => {
+                sparse.size = row + 1;
+                sparse.runs.push(Run::new(row, 1));
+
+                let runs_bytes = sparse.runs.len() * size_of::<Run>();
+                let dense_bytes =
+                    chunks_count(sparse.size, usize::BITS as usize) * size_of::<usize>();
+
+                if sparse.runs.len() > SPARSE_MIN_RUNS && runs_bytes > dense_bytes {
+                    *self = Posting::Dense(Bitmap::from(&*sparse));
+                }
+            }
+            Posting::Dense(bitmap) => {
+                if bitmap.size <= row {
+                    bitmap.resize((row + 1).max(2 * bitmap.size));
+                }
+                bitmap.set(row, true);
+            }
+        }
+    }
+
+    /// Removes a row from the posting, returning whether the row was in it.
+    fn remove(&mut self, row: usize) -> bool {
+        match self {
+            Posting::Sparse(sparse) => {
+                let found = sparse.get(row);
+                if found {
+                    sparse.set(row, false);
+                }
+                found
+            }
+            Posting::Dense(bitmap) => {
+                let found = row < bitmap.size && bitmap.get(row);
+                if found {
+                    bitmap.set(row, false);
+                }
+                found
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Posting::Sparse(sparse) => sparse.runs.is_empty(),
+            Posting::Dense(bitmap) => bitmap.chunks.iter().all(|chunk| *chunk == 0),
+        }
+    }
+
+    /// Returns the rows of the posting as a `Bitmap` of the given size.
+    fn to_bitmap(&self, rows: usize) -> Bitmap {
+        let mut bitmap = match self {
+            Posting::Sparse(sparse) => Bitmap::from(sparse),
+            Posting::Dense(bitmap) => bitmap.clone(),
+        };
+
+        bitmap.resize(rows);
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{vec, vec::Vec};
+
+    fn rows(bitmap: &Bitmap) -> Vec<usize> {
+        bitmap.iter_ones().collect()
+    }
+
+    fn column() -> BitmapIndex<&'static str> {
+        ["red", "blue", "red", "green", "blue", "red"]
+            .into_iter()
+            .map(Some)
+            .chain([None, Some("blue")])
+            .collect()
+    }
+
+    #[test]
+    fn test_bitmap_index_lookups() {
+        let index = column();
+
+        assert_eq!(index.len(), 8);
+        assert_eq!(index.cardinality(), 3);
+
+        assert_eq!(rows(&index.eq(&"red")), vec![0, 2, 5]);
+        assert_eq!(rows(&index.eq(&"yellow")), vec![]);
+        assert_eq!(rows(&index.in_set(&["red", "green"])), vec![0, 2, 3, 5]);
+        assert_eq!(rows(&index.not_eq(&"red")), vec![1, 3, 4, 7]);
+        assert_eq!(rows(&index.is_null()), vec![6]);
+        assert_eq!(rows(&index.is_not_null()), vec![0, 1, 2, 3, 4, 5, 7]);
+        assert_eq!(index.eq(&"red").size, 8);
+    }
+
+    #[test]
+    fn test_bitmap_index_remove() {
+        let mut index = column();
+
+        index.remove(2);
+        index.remove(3);
+        index.remove(6);
+
+        assert_eq!(rows(&index.eq(&"red")), vec![0, 5]);
+        assert_eq!(rows(&index.not_eq(&"red")), vec![1, 4, 7]);
+        assert_eq!(rows(&index.is_null()), vec![]);
+        assert_eq!(index.cardinality(), 2);
+        assert_eq!(index.len(), 8);
+    }
+
+    #[test]
+    fn test_bitmap_index_remove_twice() {
+        let mut index: BitmapIndex<usize> = [Some(1), None].into_iter().collect();
+
+        index.remove(1);
+        index.remove(1);
+        index.remove(0);
+        index.remove(0);
+
+        assert_eq!(rows(&index.is_null()), vec![]);
+        assert_eq!(rows(&index.is_not_null()), vec![]);
+        assert_eq!(index.cardinality(), 0);
+    }
+
+    #[test]
+    fn test_bitmap_index_frequent_values_become_dense() {
+        let index: BitmapIndex<usize> = (0..1000).map(|row| Some(row % 2)).collect();
+
+        assert!(matches!(index.postings[&0], Posting::Dense(_)));
+        assert_eq!(index.eq(&1).count_ones(), 500);
+
+        let index: BitmapIndex<usize> = (0..1000).map(|row| Some(row / 100)).collect();
+
+        assert!(matches!(index.postings[&0], Posting::Sparse(_)));
+        assert_eq!(rows(&index.eq(&3)), (300..400).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_bitmap_index_remove_from_runs() {
+        let mut posting = Posting::new();
+        for row in 0..5 {
+            posting.push(row);
+        }
+
+        assert!(posting.remove(2));
+        assert!(posting.remove(0));
+        assert!(posting.remove(4));
+        assert!(!posting.remove(4));
+
+        assert_eq!(rows(&posting.to_bitmap(5)), vec![1, 3]);
+    }
+}
@@ -0,0 +1,256 @@
+use core::mem::size_of;
+#[cfg(feature = "alloc")]
+use core::ops::{BitAnd, BitOr, BitXor, Not};
+
+#[cfg(feature = "alloc")]
+use crate::Bitmap;
+use crate::{chunks_count, low_bits_mask, ones_in_chunk};
+
+/// BitmapRef is a read-only bitmap borrowed from a buffer of bytes, such as the content
+/// of a file or a memory map, without copying it.
+///
+/// The bits are read in the layout of `Bitmap::to_bytes`: groups of 8 bits from the
+/// lowest position to the highest one, which does not depend on the endianness or the
+/// word size of the target. Operations read the buffer chunk by chunk without
+/// allocating, while the boolean operators return owned `Bitmap`s and need `alloc`.
+#[derive(Debug, Clone, Copy)]
+pub struct BitmapRef<'a> {
+    bytes: &'a [u8],
+    pub size: usize,
+}
+
+impl<'a> BitmapRef<'a> {
+    /// Creates a new `BitmapRef` of `size` bits over `bytes`, or returns `None` if the
+    /// buffer is too short.
+    pub fn new(bytes: &'a [u8], size: usize) -> Option<Self> {
+        let bytes = bytes.get(..chunks_count(size, 8))?;
+
+        Some(BitmapRef { bytes, size })
+    }
+
+    /// Creates a new `BitmapRef` over a buffer of words, or returns `None` if the buffer
+    /// is too short. The words must hold the bytes of the bitmap in the order they
+    /// were read, as when reading a file into an aligned `u64` buffer.
+    pub fn from_words(words: &'a [u64], size: usize) -> Option<Self> {
+        // SAFETY: the bytes cover the same memory as the words, with the same lifetime,
+        // and any value is a valid `u8` regardless of its alignment.
+        let bytes = unsafe {
+            core::slice::from_raw_parts(words.as_ptr() as *const u8, core::mem::size_of_val(words))
+        };
+
+        BitmapRef::new(bytes, size)
+    }
+
+    /// Creates a new `BitmapRef` over the output of `Bitmap::to_bytes`, or returns
+    /// `None` if the length of the bytes does not match the serialized size.
+    pub fn from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        let (size, bits) = bytes.split_first_chunk::<8>()?;
+        let size = usize::try_from(u64::from_le_bytes(*size)).ok()?;
+
+        if bits.len() != chunks_count(size, 8) {
+            return None;
+        }
+
+        Some(BitmapRef { bytes: bits, size })
+    }
+
+    /// Get the bit value from a given position
+    #[inline(always)]
+    pub fn get(&self, position: usize) -> bool {
+        if position >= self.size {
+            panic!("Index out of bounds");
+        }
+
+        self.bytes[position / 8] & (1 << (position % 8)) != 0
+    }
+
+    /// Count the amount of 1s in the bitmap
+    pub fn count_ones(&self) -> usize {
+        (0..self.chunks_len())
+            .map(|index| self.chunk(index).count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterate over the positions of the 1s in the bitmap, in ascending order
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + 'a {
+        let bitmap = *self;
+
+        (0..self.chunks_len()).flat_map(move |index| ones_in_chunk(index, bitmap.chunk(index)))
+    }
+
+    /// Returns the chunk at the given index, as stored by a `Bitmap`. The bits beyond
+    /// the bitmap size are 0, even if the buffer has other bits after them.
+    #[inline(always)]
+    fn chunk(&self, index: usize) -> usize {
+        let start = index * size_of::<usize>();
+        let bytes = &self.bytes[start..(start + size_of::<usize>()).min(self.bytes.len())];
+
+        let mut chunk = [0; size_of::<usize>()];
+        chunk[..bytes.len()].copy_from_slice(bytes);
+
+        usize::from_le_bytes(chunk) & low_bits_mask(self.size - index * usize::BITS as usize)
+    }
+
+    fn chunks_len(&self) -> usize {
+        chunks_count(self.size, usize::BITS as usize)
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Combines the chunks of both bitmaps with `operation`. As the `Bitmap` operators,
+    /// the result has the size of the smallest bitmap.
+    fn combine<F>(&self, size: usize, rhs: F, operation: fn(usize, usize) -> usize) -> Bitmap
+    where
+        F: Fn(usize) -> usize,
+    {
+        let size = self.size.min(size);
+        let mut bitmap = Bitmap::new(size);
+
+        for (index, chunk) in bitmap.chunks.iter_mut().enumerate() {
+            *chunk = operation(self.chunk(index), rhs(index));
+        }
+
+        bitmap.clear_padding();
+        bitmap
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&BitmapRef<'_>> for Bitmap {
+    fn from(bitmap: &BitmapRef<'_>) -> Self {
+        Bitmap {
+            chunks: (0..bitmap.chunks_len())
+                .map(|index| bitmap.chunk(index))
+                .collect(),
+            size: bitmap.size,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// Implements a boolean operator between bitmap views, and between a view and a `Bitmap`.
+macro_rules! impl_operator {
+    ($operator:ident, $method:ident, $operation:expr) => {
+        impl $operator for &BitmapRef<'_> {
+            type Output = Bitmap;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                self.combine(rhs.size, |index| rhs.chunk(index), $operation)
+            }
+        }
+
+        impl $operator<&Bitmap> for &BitmapRef<'_> {
+            type Output = Bitmap;
+
+            fn $method(self, rhs: &Bitmap) -> Self::Output {
+                self.combine(rhs.size, |index| rhs.chunks[index], $operation)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "alloc")]
+impl_operator!(BitAnd, bitand, |lhs, rhs| lhs & rhs);
+#[cfg(feature = "alloc")]
+impl_operator!(BitOr, bitor, |lhs, rhs| lhs | rhs);
+#[cfg(feature = "alloc")]
+impl_operator!(BitXor, bitxor, |lhs, rhs| lhs ^ rhs);
+
+#[cfg(feature = "alloc")]
+impl Not for &BitmapRef<'_> {
+    type Output = Bitmap;
+
+    fn not(self) -> Self::Output {
+        self.combine(self.size, |_| 0, |lhs, _| !lhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::test_util::bitmap;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_bitmap_ref_get_count_iter() {
+        let bytes = [0b1000_0101, 0b1111_1111, 0b0000_0001];
+        let bitmap = BitmapRef::new(&bytes, 17).unwrap();
+
+        assert!(bitmap.get(0));
+        assert!(!bitmap.get(1));
+        assert!(bitmap.get(7));
+        assert!(bitmap.get(16));
+        assert_eq!(bitmap.count_ones(), 12);
+        assert!(bitmap
+            .iter_ones()
+            .eq([0, 2, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]));
+        assert!(BitmapRef::new(&bytes, 25).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_bitmap_ref_ignores_bits_beyond_size() {
+        let bytes = [0xFF; 16];
+        let bitmap = BitmapRef::new(&bytes, 70).unwrap();
+
+        assert_eq!(bitmap.count_ones(), 70);
+        assert_eq!(Bitmap::from(&bitmap), !&Bitmap::new(70));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_bitmap_ref_out_of_bounds() {
+        BitmapRef::new(&[0xFF], 3).unwrap().get(3);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_bitmap_ref_from_words_and_bytes() {
+        let words = [u64::from_le_bytes([0b101, 0, 0, 0, 0, 0, 0, 0x80]), 1];
+        let bitmap = BitmapRef::from_words(&words, 65).unwrap();
+
+        assert!(bitmap.iter_ones().eq([0, 2, 63, 64]));
+
+        let owned = Bitmap::from("1000000000101");
+        let bytes = owned.to_bytes();
+        assert_eq!(Bitmap::from(&BitmapRef::from_bytes(&bytes).unwrap()), owned);
+        assert!(BitmapRef::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_bitmap_ref_operators() {
+        let (first, second) = ([0b0011], [0b0101]);
+        let first = BitmapRef::new(&first, 4).unwrap();
+        let second = BitmapRef::new(&second, 4).unwrap();
+
+        assert_eq!(&first & &second, Bitmap::from("0001"));
+        assert_eq!(&first | &second, Bitmap::from("0111"));
+        assert_eq!(&first ^ &second, Bitmap::from("0110"));
+        assert_eq!(!&first, Bitmap::from("1100"));
+        assert_eq!(&first | &Bitmap::from("11000"), Bitmap::from("1011"));
+    }
+
+    proptest! {
+        #[test]
+        #[cfg(feature = "alloc")]
+        fn test_bitmap_ref_matches_bitmap(
+            bits in prop::collection::vec(any::<bool>(), 0..300),
+            rhs_bits in prop::collection::vec(any::<bool>(), 0..300),
+        ) {
+            let (first, second) = (bitmap(&bits), bitmap(&rhs_bits));
+            let (bytes, rhs_bytes) = (first.to_bytes(), second.to_bytes());
+            let view = BitmapRef::from_bytes(&bytes).unwrap();
+            let rhs_view = BitmapRef::from_bytes(&rhs_bytes).unwrap();
+
+            prop_assert_eq!(view.count_ones(), first.count_ones());
+            prop_assert_eq!(view.iter_ones().collect::<Vec<_>>(), first.iter_ones().collect::<Vec<_>>());
+            prop_assert_eq!(&view & &rhs_view, &first & &second);
+            prop_assert_eq!(&view | &rhs_view, &first | &second);
+            prop_assert_eq!(&view ^ &second, &first ^ &second);
+            prop_assert_eq!(!&view, !&first);
+        }
+    }
+}
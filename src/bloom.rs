@@ -0,0 +1,281 @@
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::ops::{BitAnd, BitOr};
+
+use crate::Bitmap;
+
+/// BloomFilter answers whether an item may have been inserted, with no false
+/// negatives and a bounded rate of false positives. Every item sets `hashes` bits of
+/// a `Bitmap`, and an item may be contained only if all of its bits are set.
+///
+/// The bit positions of an item come from two hashes of it (double hashing): the
+/// position `i` is `h1 + i * h2`, modulo the amount of bits.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BloomFilter {
+    bits: Bitmap,
+    hashes: usize,
+}
+
+impl BloomFilter {
+    /// Creates a new `BloomFilter` sized to keep the false positive rate below
+    /// `false_positive_rate` after inserting `expected_items` items.
+    ///
+    /// For `n` items and a rate `p`, the optimal amount of bits is `-n * ln(p) / ln(2)^2`,
+    /// and the optimal amount of hashes is `bits / n * ln(2)`.
+    #[cfg(feature = "std")]
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            panic!("False positive rate must be between 0 and 1");
+        }
+
+        let items = expected_items.max(1) as f64;
+        let ln2 = core::f64::consts::LN_2;
+
+        let bits = (-items * false_positive_rate.ln() / (ln2 * ln2)).ceil();
+        let hashes = (bits / items * ln2).round();
+
+        BloomFilter::with_parameters(bits as usize, hashes as usize)
+    }
+
+    /// Creates a new `BloomFilter` with the given amount of bits and hashes per item
+    pub fn with_parameters(bits: usize, hashes: usize) -> Self {
+        BloomFilter {
+            bits: Bitmap::new(bits.max(1)),
+            hashes: hashes.max(1),
+        }
+    }
+
+    /// Amount of bits set by every item
+    pub fn hashes(&self) -> usize {
+        self.hashes
+    }
+
+    /// Bits of the filter
+    pub fn bitmap(&self) -> &Bitmap {
+        &self.bits
+    }
+
+    /// Inserts an item into the filter
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        let size = self.bits.size;
+
+        for position in positions(item, self.hashes, size) {
+            self.bits.set(position, true);
+        }
+    }
+
+    /// Whether the item may have been inserted into the filter. Items that were
+    /// inserted are always contained.
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        positions(item, self.hashes, self.bits.size).all(|position| self.bits.get(position))
+    }
+
+    /// Whether both filters have the same amount of bits and hashes, which is needed
+    /// to combine them.
+    pub fn is_compatible(&self, other: &BloomFilter) -> bool {
+        self.bits.size == other.bits.size && self.hashes == other.hashes
+    }
+
+    /// Serializes the filter into bytes: the amount of hashes as a little-endian `u64`,
+    /// followed by the bytes of the bitmap.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.hashes as u64).to_le_bytes().to_vec();
+        bytes.extend(self.bits.to_bytes());
+        bytes
+    }
+
+    /// Deserializes a filter from the bytes produced by `to_bytes`, or returns `None`
+    /// if they are not a valid filter. A filter never sets more bits per item than it
+    /// has, so larger amounts of hashes are rejected instead of looping over them.
+    pub fn from_bytes(bytes: &[u8]) -> Option<BloomFilter> {
+        let (hashes, bits) = bytes.split_first_chunk::<8>()?;
+
+        let hashes = usize::try_from(u64::from_le_bytes(*hashes)).ok()?;
+        let bits = Bitmap::from_bytes(bits)?;
+
+        if hashes == 0 || bits.size == 0 || hashes > bits.size {
+            return None;
+        }
+
+        Some(BloomFilter { bits, hashes })
+    }
+
+    fn combine(&self, rhs: &BloomFilter, operation: fn(&Bitmap, &Bitmap) -> Bitmap) -> BloomFilter {
+        if !self.is_compatible(rhs) {
+            panic!("Can not combine Bloom filters with different bits or hashes");
+        }
+
+        BloomFilter {
+            bits: operation(&self.bits, &rhs.bits),
+            hashes: self.hashes,
+        }
+    }
+}
+
+/// The union of two filters contains the items of any of them
+impl BitOr for &BloomFilter {
+    type Output = BloomFilter;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |lhs, rhs| lhs | rhs)
+    }
+}
+
+/// The intersection of two filters contains the items of both of them, with a false
+/// positive rate that may be higher than the one of a filter with only those items.
+impl BitAnd for &BloomFilter {
+    type Output = BloomFilter;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |lhs, rhs| lhs & rhs)
+    }
+}
+
+/// Calculates the bit positions of an item using double hashing.
+fn positions<T: Hash + ?Sized>(
+    item: &T,
+    hashes: usize,
+    size: usize,
+) -> impl Iterator<Item = usize> {
+    let mut hasher = Fnv1a::default();
+    item.hash(&mut hasher);
+
+    let h1 = hasher.finish();
+    // A second hash of 0 would give the same position for every index
+    let h2 = mix(h1) | 1;
+
+    (0..hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % size as u64) as usize)
+}
+
+/// Fnv1a is the 64 bits FNV-1a hash. Unlike the `std` default hasher, its output is
+/// fixed across Rust versions, which keeps serialized filters valid.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// Derives a second hash from the first one with the SplitMix64 finalizer.
+fn mix(hash: u64) -> u64 {
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_bloom_sizing() {
+        let filter = BloomFilter::new(1000, 0.01);
+
+        // 9.59 bits and 6.64 hashes per item
+        assert_eq!(filter.bitmap().size, 9586);
+        assert_eq!(filter.hashes(), 7);
+    }
+
+    #[test]
+    fn test_bloom_insert_contains() {
+        let mut filter = BloomFilter::with_parameters(959, 7);
+
+        filter.insert("apple");
+        filter.insert(&42);
+
+        assert!(filter.contains("apple"));
+        assert!(filter.contains(&42));
+        assert!(!filter.contains("pear"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_bloom_false_positive_rate() {
+        let mut filter = BloomFilter::new(1000, 0.01);
+        for item in 0..1000 {
+            filter.insert(&item);
+        }
+
+        let false_positives = (1000..11_000).filter(|item| filter.contains(item)).count();
+
+        assert!(false_positives < 200, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn test_bloom_union_intersection() {
+        let mut first = BloomFilter::with_parameters(959, 7);
+        let mut second = BloomFilter::with_parameters(959, 7);
+
+        first.insert("apple");
+        first.insert("banana");
+        second.insert("banana");
+        second.insert("cherry");
+
+        let union = &first | &second;
+        assert!(union.contains("apple") && union.contains("banana") && union.contains("cherry"));
+
+        let intersection = &first & &second;
+        assert!(intersection.contains("banana"));
+        assert!(!intersection.contains("apple"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can not combine Bloom filters")]
+    fn test_bloom_incompatible_union() {
+        let _ = &BloomFilter::with_parameters(959, 7) | &BloomFilter::with_parameters(959, 3);
+    }
+
+    #[test]
+    fn test_bloom_bytes() {
+        let mut filter = BloomFilter::with_parameters(48, 3);
+        filter.insert("apple");
+
+        let bytes = filter.to_bytes();
+        assert_eq!(BloomFilter::from_bytes(&bytes), Some(filter));
+        assert_eq!(BloomFilter::from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(BloomFilter::from_bytes(&[0; 16]), None);
+
+        // A huge amount of hashes would make every later insert loop over all of them
+        let mut bytes = bytes;
+        bytes[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(BloomFilter::from_bytes(&bytes), None);
+        bytes[..8].copy_from_slice(&49u64.to_le_bytes());
+        assert_eq!(BloomFilter::from_bytes(&bytes), None);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        #[cfg(feature = "std")]
+        fn test_bloom_has_no_false_negatives(
+            items in prop::collection::vec("[a-z]{0,12}", 0..200),
+            rate in 0.001..0.5f64,
+        ) {
+            let mut filter = BloomFilter::new(items.len(), rate);
+            for item in &items {
+                filter.insert(item);
+            }
+
+            for item in &items {
+                prop_assert!(filter.contains(item));
+            }
+        }
+    }
+}
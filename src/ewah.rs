@@ -0,0 +1,544 @@
+use alloc::{vec, vec::Vec};
+use core::ops::{BitAnd, BitOr, BitXor, Not};
+
+use crate::{bit_index, chunks_count, low_bits_mask, Bitmap};
+
+/// Amount of bits of a marker word used to store the length of its clean run
+const RUN_LENGTH_BITS: u32 = usize::BITS / 2;
+
+/// Amount of bits of a marker word used to store the amount of literal words after it
+const LITERALS_BITS: u32 = usize::BITS / 2 - 1;
+
+const MAX_RUN_LENGTH: usize = (1 << RUN_LENGTH_BITS) - 1;
+
+const MAX_LITERALS: usize = (1 << LITERALS_BITS) - 1;
+
+/// EwahBitmap stores the chunks of a `Bitmap` compressed with the Enhanced Word-Aligned
+/// Hybrid (EWAH) scheme. Consecutive chunks that are all 0s or all 1s ("clean" chunks)
+/// are collapsed into a run, while the rest are kept as they are ("literal" chunks).
+///
+/// The compressed words are a sequence of marker words, each followed by its literals:
+///   * Bit 0: the value of the bits of the clean run
+///   * Next `RUN_LENGTH_BITS` bits: the amount of chunks in the clean run
+///   * Remaining bits: the amount of literal chunks following the marker
+///
+/// Boolean operators stream both compressed bitmaps, skipping whole runs at once.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EwahBitmap {
+    words: Vec<usize>,
+    pub size: usize,
+}
+
+impl EwahBitmap {
+    /// Create a new `EwahBitmap` with a fixed size.
+    pub fn new(size: usize) -> Self {
+        let mut encoder = Encoder::new();
+        encoder.push_run(false, chunks_count(size, usize::BITS as usize));
+
+        encoder.finish(size)
+    }
+
+    /// Get the bit value from a given position
+    pub fn get(&self, position: usize) -> bool {
+        let (chunk_index, bit_index_in_chunk) = bit_index(position, usize::BITS as usize);
+
+        (self.chunk(chunk_index) & (1 << bit_index_in_chunk)) != 0
+    }
+
+    /// Count the amount of 1s in the bitmap
+    pub fn count_ones(&self) -> usize {
+        let chunk_bit_size = usize::BITS as usize;
+        let mut decoder = Decoder::new(&self.words);
+        let mut ones = 0;
+
+        while let Some(segment) = decoder.peek() {
+            match segment {
+                Segment::Run(value, length) => {
+                    if value {
+                        ones += length * chunk_bit_size;
+                    }
+                    decoder.advance(length);
+                }
+                Segment::Literal(chunk) => {
+                    ones += chunk.count_ones() as usize;
+                    decoder.advance(1);
+                }
+            }
+        }
+
+        // Discard the bits of the last chunk that lay beyond the bitmap size
+        if let Some(last) = chunks_count(self.size, chunk_bit_size).checked_sub(1) {
+            let padding = self.chunk(last) & !low_bits_mask(self.size - last * chunk_bit_size);
+            ones -= padding.count_ones() as usize;
+        }
+
+        ones
+    }
+
+    /// Amount of words used by the compressed bitmap
+    pub fn compressed_len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Decompress the chunk at the given index.
+    fn chunk(&self, index: usize) -> usize {
+        let mut decoder = Decoder::new(&self.words);
+        decoder.advance(index);
+
+        match decoder.peek() {
+            Some(segment) => segment.chunk(),
+            None => panic!("Index out of bounds"),
+        }
+    }
+
+    /// Streams both compressed bitmaps, combining their chunks with `operation`.
+    ///
+    /// Whenever a run meets chunks of the other bitmap and the run decides the result
+    /// on its own (e.g. a run of 0s for AND), the whole run is emitted at once and the
+    /// other bitmap is skipped without looking at its chunks.
+    fn combine<F>(&self, rhs: &EwahBitmap, operation: F) -> EwahBitmap
+    where
+        F: Fn(usize, usize) -> usize,
+    {
+        let size = self.size.min(rhs.size);
+        let mut remaining = chunks_count(size, usize::BITS as usize);
+
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new(&self.words);
+        let mut rhs_decoder = Decoder::new(&rhs.words);
+
+        while remaining > 0 {
+            let (Some(segment), Some(rhs_segment)) = (decoder.peek(), rhs_decoder.peek()) else {
+                break;
+            };
+
+            let length = match (segment, rhs_segment) {
+                (Segment::Run(value, length), Segment::Run(rhs_value, rhs_length)) => {
+                    let length = length.min(rhs_length).min(remaining);
+                    let chunk = operation(run_chunk(value), run_chunk(rhs_value));
+                    encoder.push_run(chunk != 0, length);
+                    length
+                }
+                (Segment::Run(value, length), Segment::Literal(_))
+                | (Segment::Literal(_), Segment::Run(value, length))
+                    if operation(run_chunk(value), 0)
+                        == operation(run_chunk(value), usize::MAX) =>
+                {
+                    let length = length.min(remaining);
+                    encoder.push_run(operation(run_chunk(value), 0) != 0, length);
+                    length
+                }
+                (segment, rhs_segment) => {
+                    encoder.push_chunk(operation(segment.chunk(), rhs_segment.chunk()));
+                    1
+                }
+            };
+
+            decoder.advance(length);
+            rhs_decoder.advance(length);
+            remaining -= length;
+        }
+
+        encoder.finish(size)
+    }
+}
+
+impl BitAnd for &EwahBitmap {
+    type Output = EwahBitmap;
+
+    #[inline(always)]
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |chunk, rhs_chunk| chunk & rhs_chunk)
+    }
+}
+
+impl BitOr for &EwahBitmap {
+    type Output = EwahBitmap;
+
+    #[inline(always)]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |chunk, rhs_chunk| chunk | rhs_chunk)
+    }
+}
+
+impl BitXor for &EwahBitmap {
+    type Output = EwahBitmap;
+
+    #[inline(always)]
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |chunk, rhs_chunk| chunk ^ rhs_chunk)
+    }
+}
+
+impl Not for &EwahBitmap {
+    type Output = EwahBitmap;
+
+    #[inline(always)]
+    fn not(self) -> Self::Output {
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new(&self.words);
+
+        while let Some(segment) = decoder.peek() {
+            match segment {
+                Segment::Run(value, length) => {
+                    encoder.push_run(!value, length);
+                    decoder.advance(length);
+                }
+                Segment::Literal(chunk) => {
+                    encoder.push_chunk(!chunk);
+                    decoder.advance(1);
+                }
+            }
+        }
+
+        encoder.finish(self.size)
+    }
+}
+
+impl From<&Bitmap> for EwahBitmap {
+    fn from(bitmap: &Bitmap) -> Self {
+        let mut encoder = Encoder::new();
+
+        for chunk in &bitmap.chunks {
+            encoder.push_chunk(*chunk);
+        }
+
+        encoder.finish(bitmap.size)
+    }
+}
+
+impl From<&EwahBitmap> for Bitmap {
+    fn from(ewah: &EwahBitmap) -> Self {
+        let mut chunks = Vec::with_capacity(chunks_count(ewah.size, usize::BITS as usize));
+        let mut decoder = Decoder::new(&ewah.words);
+
+        while let Some(segment) = decoder.peek() {
+            match segment {
+                Segment::Run(value, length) => {
+                    chunks.resize(chunks.len() + length, run_chunk(value));
+                    decoder.advance(length);
+                }
+                Segment::Literal(chunk) => {
+                    chunks.push(chunk);
+                    decoder.advance(1);
+                }
+            }
+        }
+
+        Bitmap {
+            chunks,
+            size: ewah.size,
+        }
+    }
+}
+
+impl From<&str> for EwahBitmap {
+    fn from(value: &str) -> Self {
+        EwahBitmap::from(&Bitmap::from(value))
+    }
+}
+
+/// Returns a chunk with all bits set to the value of a clean run.
+#[inline(always)]
+fn run_chunk(value: bool) -> usize {
+    if value {
+        usize::MAX
+    } else {
+        0
+    }
+}
+
+/// Marker is the decoded form of a marker word.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Marker {
+    value: bool,
+    run_length: usize,
+    literals: usize,
+}
+
+impl Marker {
+    fn decode(word: usize) -> Marker {
+        Marker {
+            value: word & 1 != 0,
+            run_length: (word >> 1) & MAX_RUN_LENGTH,
+            literals: word >> (1 + RUN_LENGTH_BITS),
+        }
+    }
+
+    fn encode(&self) -> usize {
+        self.value as usize | self.run_length << 1 | self.literals << (1 + RUN_LENGTH_BITS)
+    }
+}
+
+/// Segment is the next piece of a compressed bitmap: either the chunks left in the
+/// current clean run, or a single literal chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    Run(bool, usize),
+    Literal(usize),
+}
+
+impl Segment {
+    /// Returns the next chunk of the segment.
+    #[inline(always)]
+    fn chunk(&self) -> usize {
+        match self {
+            Segment::Run(value, _) => run_chunk(*value),
+            Segment::Literal(chunk) => *chunk,
+        }
+    }
+}
+
+/// Encoder builds the compressed words by appending chunks at the end.
+struct Encoder {
+    words: Vec<usize>,
+    marker: usize,
+}
+
+impl Encoder {
+    fn new() -> Encoder {
+        Encoder {
+            words: vec![0],
+            marker: 0,
+        }
+    }
+
+    /// Appends a chunk, merging it into the current run when it is a clean chunk.
+    #[inline(always)]
+    fn push_chunk(&mut self, chunk: usize) {
+        match chunk {
+            0 => self.push_run(false, 1),
+            usize::MAX => self.push_run(true, 1),
+            _ => self.push_literal(chunk),
+        }
+    }
+
+    /// Appends `length` clean chunks with the given value.
+    fn push_run(&mut self, value: bool, mut length: usize) {
+        while length > 0 {
+            let mut marker = Marker::decode(self.words[self.marker]);
+
+            // A run can only be extended if no literals were appended after it, and
+            // the run is empty or has the same value.
+            if marker.literals > 0
+                || (marker.run_length > 0 && marker.value != value)
+                || marker.run_length == MAX_RUN_LENGTH
+            {
+                marker = self.push_marker();
+            }
+
+            let added = length.min(MAX_RUN_LENGTH - marker.run_length);
+            marker.value = value;
+            marker.run_length += added;
+            length -= added;
+
+            self.words[self.marker] = marker.encode();
+        }
+    }
+
+    /// Appends a chunk that is neither all 0s nor all 1s.
+    fn push_literal(&mut self, chunk: usize) {
+        let mut marker = Marker::decode(self.words[self.marker]);
+        if marker.literals == MAX_LITERALS {
+            marker = self.push_marker();
+        }
+
+        marker.literals += 1;
+        self.words[self.marker] = marker.encode();
+        self.words.push(chunk);
+    }
+
+    fn push_marker(&mut self) -> Marker {
+        self.marker = self.words.len();
+        self.words.push(0);
+
+        Marker::default()
+    }
+
+    fn finish(self, size: usize) -> EwahBitmap {
+        EwahBitmap {
+            words: self.words,
+            size,
+        }
+    }
+}
+
+/// Decoder reads the compressed words from the start, segment by segment.
+struct Decoder<'a> {
+    words: &'a [usize],
+    next: usize,
+    value: bool,
+    run_length: usize,
+    literals: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(words: &'a [usize]) -> Decoder<'a> {
+        Decoder {
+            words,
+            next: 0,
+            value: false,
+            run_length: 0,
+            literals: 0,
+        }
+    }
+
+    /// Returns the current segment, or `None` if all words were read.
+    #[inline(always)]
+    fn peek(&mut self) -> Option<Segment> {
+        // Skip the markers until one with a run or literals left
+        while self.run_length == 0 && self.literals == 0 {
+            let marker = Marker::decode(*self.words.get(self.next)?);
+
+            self.next += 1;
+            self.value = marker.value;
+            self.run_length = marker.run_length;
+            self.literals = marker.literals;
+        }
+
+        if self.run_length > 0 {
+            Some(Segment::Run(self.value, self.run_length))
+        } else {
+            Some(Segment::Literal(self.words[self.next]))
+        }
+    }
+
+    /// Skips the given amount of chunks, across as many segments as needed.
+    #[inline(always)]
+    fn advance(&mut self, mut count: usize) {
+        while count > 0 && self.peek().is_some() {
+            let skipped = if self.run_length > 0 {
+                let skipped = count.min(self.run_length);
+                self.run_length -= skipped;
+                skipped
+            } else {
+                let skipped = count.min(self.literals);
+                self.literals -= skipped;
+                self.next += skipped;
+                skipped
+            };
+
+            count -= skipped;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_ewah_marker() {
+        let marker = Marker {
+            value: true,
+            run_length: MAX_RUN_LENGTH,
+            literals: MAX_LITERALS,
+        };
+
+        assert_eq!(Marker::decode(marker.encode()), marker);
+        assert_eq!(Marker::decode(0), Marker::default());
+    }
+
+    #[test]
+    fn test_ewah_new() {
+        let ewah = EwahBitmap::new(1000);
+
+        assert_eq!(ewah.compressed_len(), 1);
+        assert_eq!(ewah.count_ones(), 0);
+        assert_eq!(Bitmap::from(&ewah), Bitmap::new(1000));
+    }
+
+    #[test]
+    fn test_ewah_compresses_runs() {
+        let mut bitmap = Bitmap::new(64 * 100);
+        for position in 64 * 10..64 * 50 {
+            bitmap.set(position, true);
+        }
+        bitmap.set(64 * 60 + 3, true);
+
+        let ewah = EwahBitmap::from(&bitmap);
+
+        // Run of 0s, run of 1s, run of 0s with a literal, and a last run of 0s
+        assert_eq!(ewah.compressed_len(), 5);
+        assert_eq!(ewah.count_ones(), 64 * 40 + 1);
+        assert_eq!(Bitmap::from(&ewah), bitmap);
+    }
+
+    #[test]
+    fn test_ewah_get() {
+        let ewah = EwahBitmap::from("11001");
+
+        assert!(ewah.get(0));
+        assert!(!ewah.get(1));
+        assert!(!ewah.get(2));
+        assert!(ewah.get(3));
+        assert!(ewah.get(4));
+        assert!(!ewah.get(5));
+    }
+
+    #[test]
+    fn test_ewah_operators() {
+        assert_eq!(
+            &EwahBitmap::from("00011") & &EwahBitmap::from("01010"),
+            EwahBitmap::from("00010")
+        );
+        assert_eq!(
+            &EwahBitmap::from("01001") | &EwahBitmap::from("10010"),
+            EwahBitmap::from("11011")
+        );
+        assert_eq!(
+            &EwahBitmap::from("11001") ^ &EwahBitmap::from("01100"),
+            EwahBitmap::from("10101")
+        );
+        assert_eq!(
+            Bitmap::from(&!&EwahBitmap::from("10101")),
+            !&Bitmap::from("10101")
+        );
+    }
+
+    #[test]
+    fn test_ewah_not_counts_inside_size() {
+        let ewah = !&EwahBitmap::new(100);
+
+        assert_eq!(ewah.count_ones(), 100);
+        assert_eq!(ewah.compressed_len(), 1);
+    }
+
+    fn bitmap() -> impl Strategy<Value = Bitmap> {
+        let chunk = prop_oneof![Just(0usize), Just(usize::MAX), any::<usize>(),];
+
+        (
+            prop::collection::vec((chunk, 1..20usize), 0..20),
+            0..64usize,
+        )
+            .prop_map(|(chunks, padding)| {
+                let chunks: Vec<usize> = chunks
+                    .into_iter()
+                    .flat_map(|(chunk, times)| core::iter::repeat_n(chunk, times))
+                    .collect();
+
+                let size = (chunks.len() * usize::BITS as usize).saturating_sub(padding);
+                let mut bitmap = Bitmap::new(size);
+                let length = bitmap.chunks.len();
+                bitmap.chunks.copy_from_slice(&chunks[..length]);
+                bitmap
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_ewah_matches_bitmap(first in bitmap(), second in bitmap()) {
+            let ewah = EwahBitmap::from(&first);
+            let rhs_ewah = EwahBitmap::from(&second);
+
+            prop_assert_eq!(Bitmap::from(&ewah), first.clone());
+            prop_assert_eq!(ewah.count_ones(), first.count_ones());
+
+            prop_assert_eq!(Bitmap::from(&(&ewah & &rhs_ewah)), &first & &second);
+            prop_assert_eq!(Bitmap::from(&(&ewah | &rhs_ewah)), &first | &second);
+            prop_assert_eq!(Bitmap::from(&(&ewah ^ &rhs_ewah)), &first ^ &second);
+            prop_assert_eq!(Bitmap::from(&!&ewah), !&first);
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::ops::{BitAnd, BitOr, BitXor, Not};
+
+use crate::{low_bits_mask, Bitmap, Run, SparseBitmap};
+
+/// Expr is a boolean expression over bitmaps that is evaluated lazily.
+///
+/// Instead of allocating a bitmap for every intermediate result, `evaluate` computes
+/// the result chunk by chunk, walking the whole expression for each chunk. Before that,
+/// the expression is planned: operands known to be empty are folded away, the
+/// operands of an AND are sorted from the smallest to the largest one, and the ones of
+/// an OR from the largest to the smallest one, so that every chunk can stop evaluating
+/// operands as soon as its value is decided.
+#[derive(Debug, Clone)]
+pub enum Expr<'a> {
+    Bitmap(&'a Bitmap),
+    Sparse(&'a SparseBitmap),
+    And(Vec<Expr<'a>>),
+    Or(Vec<Expr<'a>>),
+    Xor(Box<Expr<'a>>, Box<Expr<'a>>),
+    Not(Box<Expr<'a>>),
+}
+
+impl Expr<'_> {
+    /// Size of the result, which is the smallest size of the bitmaps in the expression
+    pub fn size(&self) -> usize {
+        match self {
+            Expr::Bitmap(bitmap) => bitmap.size,
+            Expr::Sparse(sparse) => sparse.size,
+            Expr::And(operands) | Expr::Or(operands) => operands
+                .iter()
+                .map(|operand| operand.size())
+                .min()
+                .unwrap_or(0),
+            Expr::Xor(lhs, rhs) => lhs.size().min(rhs.size()),
+            Expr::Not(operand) => operand.size(),
+        }
+    }
+
+    /// Evaluates the expression into a new `Bitmap`
+    pub fn evaluate(&self) -> Bitmap {
+        let mut bitmap = Bitmap::new(self.size());
+        let (mut plan, _) = self.plan(bitmap.size);
+
+        for (index, chunk) in bitmap.chunks.iter_mut().enumerate() {
+            *chunk = plan.chunk(index);
+        }
+
+        bitmap.clear_padding();
+        bitmap
+    }
+
+    /// Builds the plan of the expression, together with an estimation of the amount of
+    /// 1s in its result.
+    fn plan(&self, size: usize) -> (Plan<'_>, usize) {
+        match self {
+            Expr::Bitmap(bitmap) => match bitmap.count_ones() {
+                0 => (Plan::Constant(false), 0),
+                ones => (Plan::Dense(&bitmap.chunks), ones.min(size)),
+            },
+            Expr::Sparse(sparse) => match sparse.runs.iter().map(|run| run.length).sum() {
+                0 => (Plan::Constant(false), 0),
+                ones => (Plan::Sparse(RunCursor::new(sparse)), ones.min(size)),
+            },
+            Expr::And(expressions) => {
+                let mut operands = Vec::with_capacity(expressions.len());
+
+                for (operand, ones) in expressions.iter().map(|operand| operand.plan(size)) {
+                    match operand {
+                        Plan::Constant(false) => return (Plan::Constant(false), 0),
+                        Plan::Constant(true) => {}
+                        operand => operands.push((operand, ones)),
+                    }
+                }
+
+                operands.sort_by_key(|(_, ones)| *ones);
+                let ones = operands.first().map_or(size, |(_, ones)| *ones);
+
+                (Plan::all(Plan::And, operands, true), ones)
+            }
+            Expr::Or(expressions) => {
+                let mut operands = Vec::with_capacity(expressions.len());
+
+                for (operand, ones) in expressions.iter().map(|operand| operand.plan(size)) {
+                    match operand {
+                        Plan::Constant(true) => return (Plan::Constant(true), size),
+                        Plan::Constant(false) => {}
+                        operand => operands.push((operand, ones)),
+                    }
+                }
+
+                operands.sort_by_key(|(_, ones)| core::cmp::Reverse(*ones));
+                let ones = operands.iter().map(|(_, ones)| ones).sum::<usize>();
+
+                (Plan::all(Plan::Or, operands, false), ones.min(size))
+            }
+            Expr::Xor(lhs, rhs) => {
+                let (lhs, lhs_ones) = lhs.plan(size);
+                let (rhs, rhs_ones) = rhs.plan(size);
+
+                match (lhs, rhs) {
+                    (Plan::Constant(false), operand) => (operand, rhs_ones),
+                    (operand, Plan::Constant(false)) => (operand, lhs_ones),
+                    (Plan::Constant(true), operand) => {
+                        (operand.negate(), size - rhs_ones.min(size))
+                    }
+                    (operand, Plan::Constant(true)) => {
+                        (operand.negate(), size - lhs_ones.min(size))
+                    }
+                    (lhs, rhs) => (
+                        Plan::Xor(Box::new(lhs), Box::new(rhs)),
+                        (lhs_ones + rhs_ones).min(size),
+                    ),
+                }
+            }
+            Expr::Not(operand) => {
+                let (operand, ones) = operand.plan(size);
+                (operand.negate(), size - ones.min(size))
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a Bitmap> for Expr<'a> {
+    fn from(bitmap: &'a Bitmap) -> Self {
+        Expr::Bitmap(bitmap)
+    }
+}
+
+impl<'a> From<&'a SparseBitmap> for Expr<'a> {
+    fn from(sparse: &'a SparseBitmap) -> Self {
+        Expr::Sparse(sparse)
+    }
+}
+
+impl<'a> BitAnd for Expr<'a> {
+    type Output = Expr<'a>;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let mut operands = match self {
+            Expr::And(operands) => operands,
+            operand => vec![operand],
+        };
+
+        match rhs {
+            Expr::And(rhs_operands) => operands.extend(rhs_operands),
+            operand => operands.push(operand),
+        }
+
+        Expr::And(operands)
+    }
+}
+
+impl<'a> BitOr for Expr<'a> {
+    type Output = Expr<'a>;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        let mut operands = match self {
+            Expr::Or(operands) => operands,
+            operand => vec![operand],
+        };
+
+        match rhs {
+            Expr::Or(rhs_operands) => operands.extend(rhs_operands),
+            operand => operands.push(operand),
+        }
+
+        Expr::Or(operands)
+    }
+}
+
+impl<'a> BitXor for Expr<'a> {
+    type Output = Expr<'a>;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Expr::Xor(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl<'a> Not for Expr<'a> {
+    type Output = Expr<'a>;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Expr::Not(operand) => *operand,
+            operand => Expr::Not(Box::new(operand)),
+        }
+    }
+}
+
+/// Plan is the planned form of an `Expr`, ready to compute its result chunk by chunk.
+#[derive(Debug)]
+enum Plan<'a> {
+    /// Chunks with all bits set to the same value
+    Constant(bool),
+    Dense(&'a [usize]),
+    Sparse(RunCursor<'a>),
+    And(Vec<Plan<'a>>),
+    Or(Vec<Plan<'a>>),
+    Xor(Box<Plan<'a>>, Box<Plan<'a>>),
+    Not(Box<Plan<'a>>),
+}
+
+impl<'a> Plan<'a> {
+    /// Builds an AND or OR plan from its sorted operands, or returns the only operand
+    /// or the constant `empty` value if there are not enough of them.
+    fn all<F>(node: F, mut operands: Vec<(Plan<'a>, usize)>, empty: bool) -> Plan<'a>
+    where
+        F: Fn(Vec<Plan<'a>>) -> Plan<'a>,
+    {
+        match operands.len() {
+            0 => Plan::Constant(empty),
+            1 => operands.remove(0).0,
+            _ => node(operands.into_iter().map(|(operand, _)| operand).collect()),
+        }
+    }
+
+    fn negate(self) -> Plan<'a> {
+        match self {
+            Plan::Constant(value) => Plan::Constant(!value),
+            Plan::Not(operand) => *operand,
+            operand => Plan::Not(Box::new(operand)),
+        }
+    }
+
+    /// Computes the chunk at the given index. Chunks must be requested in ascending
+    /// order, since run cursors only move forward.
+    #[inline(always)]
+    fn chunk(&mut self, index: usize) -> usize {
+        match self {
+            Plan::Constant(false) => 0,
+            Plan::Constant(true) => usize::MAX,
+            Plan::Dense(chunks) => chunks[index],
+            Plan::Sparse(cursor) => cursor.chunk(index),
+            Plan::And(operands) => {
+                let mut chunk = usize::MAX;
+                for operand in operands {
+                    chunk &= operand.chunk(index);
+                    if chunk == 0 {
+                        break;
+                    }
+                }
+                chunk
+            }
+            Plan::Or(operands) => {
+                let mut chunk = 0;
+                for operand in operands {
+                    chunk |= operand.chunk(index);
+                    if chunk == usize::MAX {
+                        break;
+                    }
+                }
+                chunk
+            }
+            Plan::Xor(lhs, rhs) => lhs.chunk(index) ^ rhs.chunk(index),
+            Plan::Not(operand) => !operand.chunk(index),
+        }
+    }
+}
+
+/// RunCursor computes the chunks of a `SparseBitmap` from its canonical runs, which are
+/// sorted and do not overlap.
+#[derive(Debug)]
+struct RunCursor<'a> {
+    runs: &'a [Run],
+    next: usize,
+}
+
+impl<'a> RunCursor<'a> {
+    fn new(sparse: &'a SparseBitmap) -> RunCursor<'a> {
+        debug_assert_eq!(sparse.validate(), Ok(()), "Runs must be canonical");
+
+        RunCursor {
+            runs: &sparse.runs,
+            next: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn chunk(&mut self, index: usize) -> usize {
+        let start = index * usize::BITS as usize;
+        let end = start + usize::BITS as usize;
+
+        // Runs ending before the chunk are not needed for any of the following chunks
+        while self
+            .runs
+            .get(self.next)
+            .is_some_and(|run| run.end() <= start)
+        {
+            self.next += 1;
+        }
+
+        self.runs[self.next..]
+            .iter()
+            .take_while(|run| run.start < end)
+            .fold(0, |chunk, run| {
+                let from = run.start.max(start) - start;
+                let to = run.end().min(end) - start;
+
+                chunk | (low_bits_mask(to) & !low_bits_mask(from))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_expr_evaluate() {
+        let a = Bitmap::from("1100110011");
+        let b = Bitmap::from("1010101010");
+        let c = SparseBitmap::from("0111111000");
+        let d = SparseBitmap::from("0000011111");
+
+        // (A & B) | (C & !D)
+        let expr = (Expr::from(&a) & Expr::from(&b)) | (Expr::from(&c) & !Expr::from(&d));
+
+        assert_eq!(expr.evaluate(), Bitmap::from("1111100010"));
+        assert_eq!(
+            (Expr::from(&a) ^ Expr::from(&c)).evaluate(),
+            Bitmap::from("1011001011")
+        );
+    }
+
+    #[test]
+    fn test_expr_flattens_operands() {
+        let a = Bitmap::from("110");
+        let b = Bitmap::from("011");
+        let c = Bitmap::from("111");
+
+        let Expr::And(operands) = Expr::from(&a) & Expr::from(&b) & Expr::from(&c) else {
+            panic!("Expected an AND expression");
+        };
+
+        assert_eq!(operands.len(), 3);
+        assert!(matches!(!!Expr::from(&a), Expr::Bitmap(_)));
+    }
+
+    #[test]
+    fn test_expr_plan_sorts_and_operands() {
+        let large = Bitmap::from("1111111011");
+        let small = SparseBitmap::from("0000000100");
+        let medium = Bitmap::from("0000111100");
+
+        let expr = Expr::from(&large) & Expr::from(&small) & Expr::from(&medium);
+        let (plan, ones) = expr.plan(10);
+
+        let Plan::And(operands) = plan else {
+            panic!("Expected an AND plan");
+        };
+
+        assert_eq!(ones, 1);
+        assert!(matches!(operands[0], Plan::Sparse(_)));
+        assert!(matches!(operands[1], Plan::Dense(chunks) if chunks == medium.chunks));
+        assert!(matches!(operands[2], Plan::Dense(chunks) if chunks == large.chunks));
+    }
+
+    #[test]
+    fn test_expr_plan_folds_empty_operands() {
+        let empty = Bitmap::new(10);
+        let a = Bitmap::from("1111111011");
+        let b = SparseBitmap::from("0000000100");
+
+        let and = Expr::from(&a) & Expr::from(&empty) & Expr::from(&b);
+        assert!(matches!(and.plan(10).0, Plan::Constant(false)));
+        assert_eq!(and.evaluate(), Bitmap::new(10));
+
+        let or = Expr::from(&empty) | Expr::from(&b);
+        assert!(matches!(or.plan(10).0, Plan::Sparse(_)));
+
+        let not = Expr::from(&a) | !Expr::from(&empty);
+        assert!(matches!(not.plan(10).0, Plan::Constant(true)));
+        assert_eq!(not.evaluate(), Bitmap::from("1111111111"));
+    }
+
+    #[test]
+    fn test_expr_sparse_across_chunks() {
+        let mut sparse = SparseBitmap::new(300);
+        sparse.runs = vec![Run::new(10, 100), Run::new(127, 2), Run::new(200, 64)].into();
+
+        let expr = Expr::from(&sparse);
+
+        assert_eq!(expr.evaluate(), Bitmap::from(&sparse));
+    }
+
+    #[test]
+    fn test_expr_operands_with_different_sizes() {
+        let small = Bitmap::new(10);
+        let large = !&Bitmap::new(100);
+
+        let expr = !Expr::from(&small) ^ Expr::from(&large);
+
+        assert_eq!(expr.evaluate(), Bitmap::new(10));
+        assert_eq!(
+            (Expr::from(&large) ^ !Expr::from(&small)).evaluate(),
+            Bitmap::new(10)
+        );
+    }
+
+    fn expr(leaves: usize) -> impl Strategy<Value = Node> {
+        let leaf = (0..leaves, any::<bool>()).prop_map(|(index, sparse)| Node::Leaf(index, sparse));
+
+        leaf.prop_recursive(4, 32, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 1..4).prop_map(Node::And),
+                prop::collection::vec(inner.clone(), 1..4).prop_map(Node::Or),
+                (inner.clone(), inner.clone())
+                    .prop_map(|(lhs, rhs)| Node::Xor(Box::new(lhs), Box::new(rhs))),
+                inner.prop_map(|operand| Node::Not(Box::new(operand))),
+            ]
+        })
+    }
+
+    /// Node describes an expression over the indices of the test bitmaps.
+    #[derive(Debug, Clone)]
+    enum Node {
+        Leaf(usize, bool),
+        And(Vec<Node>),
+        Or(Vec<Node>),
+        Xor(Box<Node>, Box<Node>),
+        Not(Box<Node>),
+    }
+
+    impl Node {
+        fn expr<'a>(&self, bitmaps: &'a [Bitmap], sparses: &'a [SparseBitmap]) -> Expr<'a> {
+            match self {
+                Node::Leaf(index, false) => Expr::from(&bitmaps[*index]),
+                Node::Leaf(index, true) => Expr::from(&sparses[*index]),
+                Node::And(nodes) => Expr::And(
+                    nodes
+                        .iter()
+                        .map(|node| node.expr(bitmaps, sparses))
+                        .collect(),
+                ),
+                Node::Or(nodes) => Expr::Or(
+                    nodes
+                        .iter()
+                        .map(|node| node.expr(bitmaps, sparses))
+                        .collect(),
+                ),
+                Node::Xor(lhs, rhs) => lhs.expr(bitmaps, sparses) ^ rhs.expr(bitmaps, sparses),
+                Node::Not(operand) => !operand.expr(bitmaps, sparses),
+            }
+        }
+
+        fn eager(&self, bitmaps: &[Bitmap]) -> Bitmap {
+            match self {
+                Node::Leaf(index, _) => bitmaps[*index].clone(),
+                Node::And(nodes) => nodes
+                    .iter()
+                    .map(|node| node.eager(bitmaps))
+                    .reduce(|lhs, rhs| &lhs & &rhs)
+                    .unwrap(),
+                Node::Or(nodes) => nodes
+                    .iter()
+                    .map(|node| node.eager(bitmaps))
+                    .reduce(|lhs, rhs| &lhs | &rhs)
+                    .unwrap(),
+                Node::Xor(lhs, rhs) => &lhs.eager(bitmaps) ^ &rhs.eager(bitmaps),
+                Node::Not(operand) => !&operand.eager(bitmaps),
+            }
+        }
+    }
+
+    fn bitmap(size: usize) -> impl Strategy<Value = Bitmap> {
+        let density = prop_oneof![Just(0.0), Just(0.05), Just(0.5), Just(1.0)];
+
+        density.prop_flat_map(move |density| {
+            prop::collection::vec(prop::bool::weighted(density), size)
+                .prop_map(|bits| test_util::bitmap(&bits))
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_expr_matches_eager_operators(
+            bitmaps in prop::collection::vec(bitmap(150), 3),
+            node in expr(3),
+        ) {
+            let sparses: Vec<SparseBitmap> = bitmaps.iter().map(SparseBitmap::from).collect();
+
+            let mut expected = node.eager(&bitmaps);
+            expected.clear_padding();
+
+            prop_assert_eq!(node.expr(&bitmaps, &sparses).evaluate(), expected);
+        }
+
+        #[test]
+        fn test_expr_matches_eager_operators_with_different_sizes(
+            bitmaps in prop::collection::vec((1..200usize).prop_flat_map(bitmap), 3),
+            node in expr(3),
+        ) {
+            let sparses: Vec<SparseBitmap> = bitmaps.iter().map(SparseBitmap::from).collect();
+
+            let mut expected = node.eager(&bitmaps);
+            expected.clear_padding();
+
+            prop_assert_eq!(node.expr(&bitmaps, &sparses).evaluate(), expected);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use crate::{bit_index, chunks_count, low_bits_mask, Bitmap};
+
+/// IdAllocator hands out unique IDs from a bounded space, reusing the freed ones.
+///
+/// Allocated IDs are the 1s of a `Bitmap`. A second level keeps one bit per chunk of
+/// it, set when the chunk has no free ID left, so looking for a free ID skips full
+/// chunks 64 at a time instead of rescanning them. Allocations continue from the
+/// chunk of the last allocation (next-fit), and wrap around to the start.
+#[derive(Debug, Clone)]
+pub struct IdAllocator {
+    used: Bitmap,
+    full: Bitmap,
+    cursor: usize,
+    allocated: usize,
+    growable: bool,
+}
+
+impl IdAllocator {
+    /// Creates a new `IdAllocator` with IDs in `0..capacity`
+    pub fn new(capacity: usize) -> Self {
+        IdAllocator {
+            used: Bitmap::new(capacity),
+            full: Bitmap::new(chunks_count(capacity, usize::BITS as usize)),
+            cursor: 0,
+            allocated: 0,
+            growable: false,
+        }
+    }
+
+    /// Creates a new `IdAllocator` with IDs in `0..capacity`, that doubles its
+    /// capacity whenever it runs out of IDs.
+    pub fn growable(capacity: usize) -> Self {
+        IdAllocator {
+            growable: true,
+            ..IdAllocator::new(capacity)
+        }
+    }
+
+    /// Amount of IDs that can be allocated without growing
+    pub fn capacity(&self) -> usize {
+        self.used.size
+    }
+
+    /// Amount of allocated IDs
+    pub fn len(&self) -> usize {
+        self.allocated
+    }
+
+    /// Whether no ID is allocated
+    pub fn is_empty(&self) -> bool {
+        self.allocated == 0
+    }
+
+    /// Whether the given ID is allocated
+    pub fn is_allocated(&self, id: usize) -> bool {
+        id < self.used.size && self.used.get(id)
+    }
+
+    /// Allocates a free ID, or returns `None` if there is none left and the allocator
+    /// can not grow.
+    pub fn allocate(&mut self) -> Option<usize> {
+        let chunk = match self.free_chunk(self.cursor) {
+            Some(chunk) => chunk,
+            None if self.growable => {
+                let chunk = chunks_count(self.used.size, usize::BITS as usize);
+                self.grow((2 * self.used.size).max(1));
+                self.free_chunk(chunk.saturating_sub(1))?
+            }
+            None => return None,
+        };
+
+        // Chunks without the full bit have a free ID inside the capacity
+        let id = chunk * usize::BITS as usize + self.used.chunks[chunk].trailing_ones() as usize;
+
+        self.mark(id, id + 1);
+        self.cursor = chunk;
+
+        Some(id)
+    }
+
+    /// Allocates the given ID, returning whether it was free. IDs beyond the capacity
+    /// grow the allocator if possible.
+    pub fn allocate_at(&mut self, id: usize) -> bool {
+        if id >= self.used.size {
+            if !self.growable {
+                panic!("Index out of bounds");
+            }
+            self.grow((id + 1).max(2 * self.used.size));
+        }
+
+        if self.used.get(id) {
+            return false;
+        }
+
+        self.mark(id, id + 1);
+        true
+    }
+
+    /// Allocates `count` consecutive IDs, returning the first of them. The lowest
+    /// free range is used (first-fit), so that the high IDs stay free for large
+    /// allocations.
+    pub fn allocate_contiguous(&mut self, count: usize) -> Option<usize> {
+        if count == 0 {
+            return None;
+        }
+
+        loop {
+            if let Some(start) = self.free_range(count) {
+                self.mark(start, start + count);
+                return Some(start);
+            }
+
+            if !self.growable {
+                return None;
+            }
+            self.grow((2 * self.used.size).max(self.used.size + count));
+        }
+    }
+
+    /// Frees the given ID, returning whether it was allocated
+    pub fn free(&mut self, id: usize) -> bool {
+        if id >= self.used.size {
+            panic!("Index out of bounds");
+        }
+
+        if !self.used.get(id) {
+            return false;
+        }
+
+        self.used.set(id, false);
+        self.full.set(id / usize::BITS as usize, false);
+        self.allocated -= 1;
+
+        true
+    }
+
+    /// Marks the IDs in `start..end` as allocated, updating the full chunks.
+    fn mark(&mut self, start: usize, end: usize) {
+        self.used.fill(start, end);
+        self.allocated += end - start;
+
+        let first_chunk = start / usize::BITS as usize;
+        let last_chunk = (end - 1) / usize::BITS as usize;
+
+        for chunk in first_chunk..=last_chunk {
+            if self.is_full(chunk) {
+                self.full.set(chunk, true);
+            }
+        }
+    }
+
+    /// Whether every ID of the chunk at the given index is allocated
+    fn is_full(&self, chunk: usize) -> bool {
+        let free = !self.used.chunks[chunk];
+        self.used.masked_chunk(chunk, free) == 0
+    }
+
+    /// Finds the first chunk with a free ID, starting from the given chunk and
+    /// wrapping around to the start.
+    fn free_chunk(&self, from: usize) -> Option<usize> {
+        let from = from.min(self.full.size);
+
+        self.free_chunk_in(from, self.full.size)
+            .or_else(|| self.free_chunk_in(0, from))
+    }
+
+    /// Finds the first chunk with a free ID in `start..end`, looking at the full bits
+    /// of 64 chunks at once.
+    fn free_chunk_in(&self, start: usize, end: usize) -> Option<usize> {
+        let mut index = start;
+
+        while index < end {
+            let (word, bit) = bit_index(index, usize::BITS as usize);
+            let free = !self.full.chunks[word] & !low_bits_mask(bit);
+
+            if free != 0 {
+                let chunk = word * usize::BITS as usize + free.trailing_zeros() as usize;
+                return (chunk < end).then_some(chunk);
+            }
+
+            index = (word + 1) * usize::BITS as usize;
+        }
+
+        None
+    }
+
+    /// Finds the lowest start of `count` consecutive free IDs.
+    ///
+    /// Free IDs are consumed range by range from the chunks that are not full, and a
+    /// range reaching the end of a chunk continues in the next one.
+    fn free_range(&self, count: usize) -> Option<usize> {
+        let mut range_start = 0;
+        let mut range_end = 0;
+
+        for chunk in 0..self.full.size {
+            if self.full.get(chunk) {
+                continue;
+            }
+
+            let offset = chunk * usize::BITS as usize;
+            let mut free = self.used.masked_chunk(chunk, !self.used.chunks[chunk]);
+
+            while free != 0 {
+                let start = free.trailing_zeros() as usize;
+                let length = (free >> start).trailing_ones() as usize;
+
+                if offset + start != range_end {
+                    range_start = offset + start;
+                }
+                range_end = offset + start + length;
+
+                if range_end - range_start >= count {
+                    return Some(range_start);
+                }
+
+                free &= !low_bits_mask(start + length);
+            }
+        }
+
+        None
+    }
+
+    /// Grows the allocator to the given capacity. The previous last chunk gets new
+    /// free IDs, unless its IDs already filled it up to the chunk bit size.
+    fn grow(&mut self, capacity: usize) {
+        let last_chunk = self.full.size.checked_sub(1);
+
+        let chunks = chunks_count(capacity, usize::BITS as usize);
+        self.used.resize(capacity);
+        self.full.resize(chunks);
+
+        for chunk in last_chunk.into_iter().chain(chunks.checked_sub(1)) {
+            let full = self.is_full(chunk);
+            self.full.set(chunk, full);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeSet;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_id_allocator_allocate_and_free() {
+        let mut ids = IdAllocator::new(3);
+
+        assert_eq!(ids.allocate(), Some(0));
+        assert_eq!(ids.allocate(), Some(1));
+        assert_eq!(ids.allocate(), Some(2));
+        assert_eq!(ids.allocate(), None);
+        assert_eq!(ids.len(), 3);
+
+        assert!(ids.free(1));
+        assert!(!ids.free(1));
+        assert_eq!(ids.allocate(), Some(1));
+        assert!(ids.is_allocated(1));
+    }
+
+    #[test]
+    fn test_id_allocator_next_fit() {
+        let mut ids = IdAllocator::new(200);
+
+        for _ in 0..150 {
+            ids.allocate();
+        }
+        ids.free(10);
+        ids.free(140);
+
+        // The search continues from the chunk of the last allocation
+        assert_eq!(ids.allocate(), Some(140));
+        assert_eq!(ids.allocate(), Some(150));
+
+        for _ in 151..200 {
+            ids.allocate();
+        }
+        assert_eq!(ids.allocate(), Some(10));
+        assert_eq!(ids.allocate(), None);
+    }
+
+    #[test]
+    fn test_id_allocator_skips_full_chunks() {
+        let mut ids = IdAllocator::new(10_000);
+
+        assert_eq!(ids.allocate_contiguous(9_000), Some(0));
+        assert_eq!(ids.full.count_ones(), 9_000 / 64);
+        assert_eq!(ids.allocate(), Some(9_000));
+
+        ids.free(64);
+        assert!(!ids.full.get(1));
+        assert!(ids.allocate_at(64));
+        assert!(ids.full.get(1));
+        assert!(!ids.allocate_at(64));
+    }
+
+    #[test]
+    fn test_id_allocator_allocate_contiguous() {
+        let mut ids = IdAllocator::new(300);
+
+        assert_eq!(ids.allocate_contiguous(60), Some(0));
+        assert_eq!(ids.allocate_contiguous(10), Some(60));
+        ids.free(30);
+        ids.free(31);
+
+        // The free range crosses the chunks boundary at 64
+        assert_eq!(ids.allocate_contiguous(2), Some(30));
+        assert_eq!(ids.allocate_contiguous(100), Some(70));
+        assert_eq!(ids.allocate_contiguous(200), None);
+        assert_eq!(ids.allocate_contiguous(130), Some(170));
+        assert_eq!(ids.len(), 300);
+    }
+
+    #[test]
+    fn test_id_allocator_grows() {
+        let mut ids = IdAllocator::growable(2);
+
+        assert_eq!(ids.allocate(), Some(0));
+        assert_eq!(ids.allocate(), Some(1));
+        assert_eq!(ids.allocate(), Some(2));
+        assert_eq!(ids.capacity(), 4);
+
+        assert!(ids.allocate_at(100));
+        assert_eq!(ids.capacity(), 101);
+        assert_eq!(ids.allocate_contiguous(150), Some(101));
+        assert_eq!(ids.len(), 154);
+
+        let mut ids = IdAllocator::growable(0);
+        assert_eq!(ids.allocate(), Some(0));
+    }
+
+    #[test]
+    fn test_id_allocator_grows_from_full_chunk() {
+        let mut ids = IdAllocator::growable(64);
+
+        assert_eq!(ids.allocate_contiguous(64), Some(0));
+        assert_eq!(ids.allocate(), Some(64));
+
+        // The first chunk was the last one, and is still full after growing
+        assert!(ids.full.get(0));
+        assert!(!ids.full.get(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_id_allocator_allocate_at_out_of_bounds() {
+        IdAllocator::new(10).allocate_at(10);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_id_allocator_matches_set(
+            operations in prop::collection::vec((0..4u8, 0..300usize), 1..400),
+            growable in any::<bool>(),
+        ) {
+            let mut ids = if growable { IdAllocator::growable(100) } else { IdAllocator::new(100) };
+            let mut allocated = BTreeSet::new();
+
+            for (operation, value) in operations {
+                match operation {
+                    0 => {
+                        if let Some(id) = ids.allocate() {
+                            prop_assert!(allocated.insert(id));
+                        } else {
+                            prop_assert_eq!(allocated.len(), ids.capacity());
+                        }
+                    }
+                    1 => {
+                        let count = value % 20 + 1;
+                        if let Some(start) = ids.allocate_contiguous(count) {
+                            for id in start..start + count {
+                                prop_assert!(allocated.insert(id));
+                            }
+                        }
+                    }
+                    2 if value < ids.capacity() => {
+                        prop_assert_eq!(ids.free(value), allocated.remove(&value));
+                    }
+                    3 if value < ids.capacity() || growable => {
+                        prop_assert_eq!(ids.allocate_at(value), allocated.insert(value));
+                    }
+                    _ => {}
+                }
+
+                prop_assert_eq!(ids.len(), allocated.len());
+                prop_assert_eq!(ids.used.iter_ones().collect::<BTreeSet<_>>(), allocated.clone());
+                for chunk in 0..ids.full.size {
+                    prop_assert_eq!(ids.full.get(chunk), ids.is_full(chunk));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use core::ops::{BitAnd, BitOr, BitXor, Not};
+
+#[cfg(feature = "alloc")]
+use crate::Bitmap;
+use crate::{bit_index, chunks_count, low_bits_mask, ones_in_chunk};
+
+/// InlineBitmap is a dense bitmap stored in an array of `CHUNKS` chunks, so that it
+/// lives on the stack or inside another value and never allocates.
+///
+/// The size can change up to the capacity of the array, which is `CHUNKS * 64` bits.
+/// The bits beyond the size are always 0.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InlineBitmap<const CHUNKS: usize> {
+    chunks: [usize; CHUNKS],
+    pub size: usize,
+}
+
+impl<const CHUNKS: usize> InlineBitmap<CHUNKS> {
+    /// Maximum amount of bits of the bitmap
+    pub const CAPACITY: usize = CHUNKS * usize::BITS as usize;
+
+    /// Create a new `InlineBitmap` with a fixed size, which must fit in the capacity.
+    pub const fn new(size: usize) -> Self {
+        if size > Self::CAPACITY {
+            panic!("Size exceeds the inline capacity");
+        }
+
+        InlineBitmap {
+            chunks: [0; CHUNKS],
+            size,
+        }
+    }
+
+    /// Get the bit value from a given position
+    #[inline(always)]
+    pub fn get(&self, position: usize) -> bool {
+        if position >= self.size {
+            panic!("Index out of bounds");
+        }
+
+        let (chunk_index, bit_index_in_chunk) = bit_index(position, usize::BITS as usize);
+
+        self.chunks[chunk_index] & (1 << bit_index_in_chunk) != 0
+    }
+
+    /// Set a bit value in a given position
+    #[inline(always)]
+    pub fn set(&mut self, position: usize, value: bool) {
+        if position >= self.size {
+            panic!("Index out of bounds");
+        }
+
+        let (chunk_index, bit_index_in_chunk) = bit_index(position, usize::BITS as usize);
+
+        if value {
+            self.chunks[chunk_index] |= 1 << bit_index_in_chunk;
+        } else {
+            self.chunks[chunk_index] &= !(1 << bit_index_in_chunk);
+        }
+    }
+
+    /// Count the amount of 1s in the bitmap
+    pub fn count_ones(&self) -> usize {
+        self.chunks
+            .iter()
+            .map(|chunk| chunk.count_ones() as usize)
+            .sum()
+    }
+
+    /// Change the size of the bitmap, which must fit in the capacity. New positions
+    /// are set to 0.
+    pub fn resize(&mut self, size: usize) {
+        if size > Self::CAPACITY {
+            panic!("Size exceeds the inline capacity");
+        }
+
+        self.size = size.min(self.size);
+        self.clear_padding();
+        self.size = size;
+    }
+
+    /// Iterate over the positions of the 1s in the bitmap, in ascending order
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.chunks
+            .iter()
+            .enumerate()
+            .flat_map(|(index, chunk)| ones_in_chunk(index, *chunk))
+    }
+
+    /// Sets a 0 to every bit that lays beyond the bitmap size.
+    fn clear_padding(&mut self) {
+        let chunks = chunks_count(self.size, usize::BITS as usize);
+
+        for (index, chunk) in self
+            .chunks
+            .iter_mut()
+            .enumerate()
+            .skip(chunks.saturating_sub(1))
+        {
+            *chunk &= low_bits_mask(self.size.saturating_sub(index * usize::BITS as usize));
+        }
+    }
+
+    /// Combines the chunks of both bitmaps with `operation`. As the `Bitmap` operators,
+    /// the result has the size of the smallest bitmap.
+    fn combine(&self, rhs: &Self, operation: fn(usize, usize) -> usize) -> Self {
+        let mut bitmap = InlineBitmap::new(self.size.min(rhs.size));
+
+        for (index, chunk) in bitmap.chunks.iter_mut().enumerate() {
+            *chunk = operation(self.chunks[index], rhs.chunks[index]);
+        }
+
+        bitmap.clear_padding();
+        bitmap
+    }
+}
+
+/// Implements a boolean operator between inline bitmaps of the same capacity.
+macro_rules! impl_operator {
+    ($operator:ident, $method:ident, $operation:expr) => {
+        impl<const CHUNKS: usize> $operator for &InlineBitmap<CHUNKS> {
+            type Output = InlineBitmap<CHUNKS>;
+
+            #[inline(always)]
+            fn $method(self, rhs: Self) -> Self::Output {
+                self.combine(rhs, $operation)
+            }
+        }
+    };
+}
+
+impl_operator!(BitAnd, bitand, |lhs, rhs| lhs & rhs);
+impl_operator!(BitOr, bitor, |lhs, rhs| lhs | rhs);
+impl_operator!(BitXor, bitxor, |lhs, rhs| lhs ^ rhs);
+
+impl<const CHUNKS: usize> Not for &InlineBitmap<CHUNKS> {
+    type Output = InlineBitmap<CHUNKS>;
+
+    #[inline(always)]
+    fn not(self) -> Self::Output {
+        self.combine(self, |lhs, _| !lhs)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const CHUNKS: usize> From<&InlineBitmap<CHUNKS>> for Bitmap {
+    fn from(bitmap: &InlineBitmap<CHUNKS>) -> Self {
+        let chunks = chunks_count(bitmap.size, usize::BITS as usize);
+
+        Bitmap {
+            chunks: bitmap.chunks[..chunks].to_vec(),
+            size: bitmap.size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::test_util::build;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_inline_bitmap_get_set() {
+        let mut bitmap = InlineBitmap::<2>::new(100);
+
+        bitmap.set(3, true);
+        bitmap.set(64, true);
+        bitmap.set(99, true);
+        bitmap.set(3, false);
+
+        assert!(!bitmap.get(3));
+        assert!(bitmap.get(64));
+        assert_eq!(bitmap.count_ones(), 2);
+        assert!(bitmap.iter_ones().eq([64, 99]));
+        assert_eq!(InlineBitmap::<2>::CAPACITY, 128);
+    }
+
+    #[test]
+    fn test_inline_bitmap_resize_clears_bits() {
+        let mut bitmap = !&InlineBitmap::<2>::new(100);
+        assert_eq!(bitmap.count_ones(), 100);
+
+        bitmap.resize(10);
+        bitmap.resize(128);
+
+        assert_eq!(bitmap.count_ones(), 10);
+        assert!(!bitmap.get(127));
+    }
+
+    #[test]
+    #[should_panic(expected = "Size exceeds the inline capacity")]
+    fn test_inline_bitmap_over_capacity() {
+        InlineBitmap::<1>::new(65);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_inline_bitmap_out_of_bounds() {
+        InlineBitmap::<1>::new(10).set(10, true);
+    }
+
+    proptest! {
+        #[test]
+        #[cfg(feature = "alloc")]
+        fn test_inline_bitmap_matches_bitmap(
+            bits in prop::collection::vec(any::<bool>(), 0..=256),
+            rhs_bits in prop::collection::vec(any::<bool>(), 0..=256),
+        ) {
+            let inline = |bits: &[bool]| build(bits, InlineBitmap::<4>::new, InlineBitmap::set);
+            let (first, second) = (inline(&bits), inline(&rhs_bits));
+            let (first_bitmap, second_bitmap) = (Bitmap::from(&first), Bitmap::from(&second));
+
+            prop_assert_eq!(first.count_ones(), first_bitmap.count_ones());
+            prop_assert_eq!(first.iter_ones().collect::<Vec<_>>(), first_bitmap.iter_ones().collect::<Vec<_>>());
+            prop_assert_eq!(Bitmap::from(&(&first & &second)), &first_bitmap & &second_bitmap);
+            prop_assert_eq!(Bitmap::from(&(&first | &second)), &first_bitmap | &second_bitmap);
+            prop_assert_eq!(Bitmap::from(&(&first ^ &second)), &first_bitmap ^ &second_bitmap);
+            prop_assert_eq!(Bitmap::from(&!&first), !&first_bitmap);
+        }
+    }
+}
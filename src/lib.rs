@@ -1,12 +1,93 @@
-use std::ops::{BitAnd, BitOr, BitXor, Not};
-
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    iter::{Product, Sum},
+    mem::size_of,
+    ops::{BitAnd, BitOr, BitXor, Index, Not, Range},
+};
+#[cfg(feature = "alloc")]
+use run_store::{Run, Store};
+
+#[cfg(feature = "alloc")]
+mod atomic;
+#[cfg(feature = "alloc")]
+mod bit_grid;
+#[cfg(feature = "alloc")]
+mod bit_matrix;
+#[cfg(feature = "alloc")]
+mod bit_sliced;
+#[cfg(feature = "std")]
+mod bitmap_index;
+mod bitmap_ref;
+#[cfg(feature = "alloc")]
+mod bloom;
+#[cfg(feature = "alloc")]
+mod ewah;
+#[cfg(feature = "alloc")]
+mod expr;
+#[cfg(feature = "alloc")]
+mod id_allocator;
+mod inline;
+#[cfg(all(test, feature = "alloc"))]
+mod oracle;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "alloc")]
+mod roaring;
+#[cfg(feature = "alloc")]
+mod run_store;
+#[cfg(all(test, feature = "alloc"))]
+mod test_util;
+#[cfg(feature = "alloc")]
+mod tree_sparse;
+
+#[cfg(feature = "alloc")]
+pub use atomic::AtomicBitmap;
+#[cfg(feature = "alloc")]
+pub use bit_grid::BitGrid;
+#[cfg(feature = "alloc")]
+pub use bit_matrix::BitMatrix;
+#[cfg(feature = "alloc")]
+pub use bit_sliced::BitSlicedIndex;
+#[cfg(feature = "std")]
+pub use bitmap_index::BitmapIndex;
+pub use bitmap_ref::BitmapRef;
+#[cfg(feature = "alloc")]
+pub use bloom::BloomFilter;
+#[cfg(feature = "alloc")]
+pub use ewah::EwahBitmap;
+#[cfg(feature = "alloc")]
+pub use expr::Expr;
+#[cfg(feature = "alloc")]
+pub use id_allocator::IdAllocator;
+pub use inline::InlineBitmap;
+#[cfg(feature = "alloc")]
+pub use roaring::RoaringBitmap;
+#[cfg(feature = "alloc")]
+pub use run_store::{RunStore, VecRuns};
+#[cfg(feature = "alloc")]
+pub use tree_sparse::{TreeRuns, TreeSparseBitmap};
+
+#[cfg(feature = "alloc")]
 /// Bitmap stores a bitmap in chunks of 64 bits
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Bitmap {
     chunks: Vec<usize>,
     pub size: usize,
 }
 
+#[cfg(feature = "alloc")]
 impl Bitmap {
     /// Create a new `Bitmap` with a fixed size.
     pub fn new(size: usize) -> Self {
@@ -46,6 +127,56 @@ impl Bitmap {
         }
     }
 
+    /// Count the amount of 1s in the bitmap
+    pub fn count_ones(&self) -> usize {
+        self.chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| self.masked_chunk(index, *chunk).count_ones() as usize)
+            .sum()
+    }
+
+    /// Change the size of the bitmap. New positions are set to 0.
+    pub fn resize(&mut self, size: usize) {
+        // Clear the bits beyond the current size, so that they do not show up as
+        // new positions when growing
+        self.clear_padding();
+
+        let chunks = chunks_count(size, usize::BITS as usize);
+        self.chunks.resize(chunks, 0);
+        self.size = size;
+
+        self.clear_padding();
+    }
+
+    /// Iterate over the positions of the 1s in the bitmap, in ascending order
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.chunks
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, chunk)| self.chunk_ones(index, *chunk))
+    }
+
+    /// Iterate over the runs of consecutive 1s in the bitmap as ranges of positions,
+    /// in ascending order. Runs that cross the border between chunks are merged.
+    pub fn runs(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let mut runs = self
+            .masked_chunks()
+            .enumerate()
+            .flat_map(|(index, chunk)| runs_in_chunk(index, chunk))
+            .peekable();
+
+        core::iter::from_fn(move || {
+            let mut run = runs.next()?;
+
+            while let Some(next) = runs.next_if(|next| next.start == run.end) {
+                run.end = next.end;
+            }
+
+            Some(run)
+        })
+    }
+
     /// Sets a 1 to the corresponding chunk and bit position.
     ///
     /// This operation can be implemented by the following steps:
@@ -76,11 +207,140 @@ impl Bitmap {
         self.chunks[chunk] &= !(1 << bit)
     }
 
+    /// Serializes the bitmap into bytes: the size as a little-endian `u64`, followed by
+    /// the bits in groups of 8, from the lowest position to the highest one.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + chunks_count(self.size, 8));
+        bytes.extend_from_slice(&(self.size as u64).to_le_bytes());
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            bytes.extend_from_slice(&self.masked_chunk(index, *chunk).to_le_bytes());
+        }
+
+        // The last chunk may have more bytes than needed for the bitmap size
+        bytes.truncate(8 + chunks_count(self.size, 8));
+        bytes
+    }
+
+    /// Deserializes a bitmap from the bytes produced by `to_bytes`, or returns `None`
+    /// if their length does not match the serialized size.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Bitmap> {
+        let (size, bits) = bytes.split_first_chunk::<8>()?;
+        let size = usize::try_from(u64::from_le_bytes(*size)).ok()?;
+
+        if bits.len() != chunks_count(size, 8) {
+            return None;
+        }
+
+        let mut bitmap = Bitmap::new(size);
+        for (chunk, bytes) in bitmap
+            .chunks
+            .iter_mut()
+            .zip(bits.chunks(size_of::<usize>()))
+        {
+            let mut chunk_bytes = [0; size_of::<usize>()];
+            chunk_bytes[..bytes.len()].copy_from_slice(bytes);
+
+            *chunk = usize::from_le_bytes(chunk_bytes);
+        }
+
+        bitmap.clear_padding();
+        Some(bitmap)
+    }
+
+    /// Writes the bytes produced by `to_bytes` into `writer`.
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    /// Reads a bitmap written by `write_to` from `reader`, stopping at its last byte.
+    #[cfg(feature = "std")]
+    pub fn read_from<R: std::io::Read>(mut reader: R) -> std::io::Result<Bitmap> {
+        use std::io::{Error, ErrorKind, Read};
+
+        let mut bytes = vec![0; 8];
+        reader.read_exact(&mut bytes)?;
+
+        // The bytes are read as they come, instead of allocating for an untrusted size
+        let size = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        reader.take(size.div_ceil(8)).read_to_end(&mut bytes)?;
+
+        Bitmap::from_bytes(&bytes)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Bitmap bytes are incomplete"))
+    }
+
+    /// Panics if the bitmap does not have a chunk per 64 bits, or has 1s beyond its size.
+    #[cfg(fuzzing)]
+    pub fn assert_invariants(&self) {
+        let chunks = chunks_count(self.size, usize::BITS as usize);
+        assert_eq!(self.chunks.len(), chunks, "Chunks do not match the size");
+
+        if let Some(index) = chunks.checked_sub(1) {
+            let chunk = self.chunks[index];
+            assert_eq!(
+                self.masked_chunk(index, chunk),
+                chunk,
+                "Padding bits are not 0"
+            );
+        }
+    }
+
+    /// Iterate over the positions of the 1s in the chunk at the given index.
+    fn chunk_ones(&self, index: usize, chunk: usize) -> impl Iterator<Item = usize> {
+        ones_in_chunk(index, self.masked_chunk(index, chunk))
+    }
+
+    /// Sets a 1 to every position in `start..end`, filling whole chunks at once.
+    fn fill(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+
+        let (first_chunk, first_bit) = bit_index(start, usize::BITS as usize);
+        let (last_chunk, last_bit) = bit_index(end, usize::BITS as usize);
+
+        if first_chunk == last_chunk {
+            self.chunks[first_chunk] |= low_bits_mask(last_bit) & !low_bits_mask(first_bit);
+            return;
+        }
+
+        self.chunks[first_chunk] |= !low_bits_mask(first_bit);
+        for chunk in &mut self.chunks[first_chunk + 1..last_chunk] {
+            *chunk = usize::MAX;
+        }
+        if last_bit > 0 {
+            self.chunks[last_chunk] |= low_bits_mask(last_bit);
+        }
+    }
+
+    /// Returns the chunk at the given index without the bits that lay beyond the bitmap size.
+    #[inline(always)]
+    fn masked_chunk(&self, index: usize, chunk: usize) -> usize {
+        chunk & low_bits_mask(self.size - index * usize::BITS as usize)
+    }
+
+    /// Iterate over the chunks without the bits that lay beyond the bitmap size.
+    fn masked_chunks(&self) -> impl Iterator<Item = usize> + '_ {
+        self.chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| self.masked_chunk(index, *chunk))
+    }
+
+    /// Sets a 0 to every bit that lays beyond the bitmap size.
+    fn clear_padding(&mut self) {
+        if let Some(index) = self.chunks.len().checked_sub(1) {
+            self.chunks[index] = self.masked_chunk(index, self.chunks[index]);
+        }
+    }
+
     fn chunks_with_size(size: usize) -> Vec<usize> {
         vec![0; chunks_count(size, usize::BITS as usize)]
     }
 }
 
+#[cfg(feature = "alloc")]
 impl BitAnd for &Bitmap {
     type Output = Bitmap;
 
@@ -93,10 +353,13 @@ impl BitAnd for &Bitmap {
             *chunk = self.chunks[id] & rhs.chunks[id];
         }
 
-        Bitmap { chunks, size }
+        let mut bitmap = Bitmap { chunks, size };
+        bitmap.clear_padding();
+        bitmap
     }
 }
 
+#[cfg(feature = "alloc")]
 impl BitOr for &Bitmap {
     type Output = Bitmap;
 
@@ -109,10 +372,13 @@ impl BitOr for &Bitmap {
             *chunk = self.chunks[id] | rhs.chunks[id];
         }
 
-        Bitmap { chunks, size }
+        let mut bitmap = Bitmap { chunks, size };
+        bitmap.clear_padding();
+        bitmap
     }
 }
 
+#[cfg(feature = "alloc")]
 impl BitXor for &Bitmap {
     type Output = Bitmap;
 
@@ -125,10 +391,13 @@ impl BitXor for &Bitmap {
             *chunk = self.chunks[id] ^ rhs.chunks[id];
         }
 
-        Bitmap { chunks, size }
+        let mut bitmap = Bitmap { chunks, size };
+        bitmap.clear_padding();
+        bitmap
     }
 }
 
+#[cfg(feature = "alloc")]
 impl Not for &Bitmap {
     type Output = Bitmap;
 
@@ -136,13 +405,16 @@ impl Not for &Bitmap {
     fn not(self) -> Self::Output {
         let chunks = self.chunks.iter().map(|chunk| !chunk).collect();
 
-        Bitmap {
+        let mut bitmap = Bitmap {
             chunks,
             size: self.size,
-        }
+        };
+        bitmap.clear_padding();
+        bitmap
     }
 }
 
+#[cfg(feature = "alloc")]
 impl From<&str> for Bitmap {
     fn from(value: &str) -> Self {
         let mut bitmap = Bitmap::new(value.len());
@@ -159,36 +431,107 @@ impl From<&str> for Bitmap {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<S: RunStore> From<&SparseBitmap<S>> for Bitmap {
+    fn from(sparse: &SparseBitmap<S>) -> Self {
+        let mut bitmap = Bitmap::new(sparse.size);
+
+        for (start, end) in sparse.ranges() {
+            bitmap.fill(start, end);
+        }
+
+        bitmap
+    }
+}
+
+#[cfg(feature = "alloc")]
 // SparseBitmap is a bitmap representation optimized for sparse bitmap distributions.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct SparseBitmap {
-    runs: Vec<Run>,
+// Its runs of 1s are kept in the store `S`, a `Vec` by default.
+#[derive(Debug, Clone, Default)]
+pub struct SparseBitmap<S = VecRuns> {
+    runs: S,
     pub size: usize,
 }
 
+#[cfg(feature = "alloc")]
 impl SparseBitmap {
     /// Creates a new `SparseBitmap` with a fixed size
     pub fn new(size: usize) -> SparseBitmap {
-        SparseBitmap {
-            runs: Vec::new(),
-            size,
+        SparseBitmap::with_store(size, VecRuns::default())
+    }
+
+    /// Creates a `SparseBitmap` from ranges of 1s sorted by their start, in a single
+    /// pass. Ranges that overlap or touch are merged, and empty ones are ignored.
+    ///
+    /// Fails with the index of the first range that starts before the previous one
+    /// or ends beyond the size.
+    pub fn from_sorted_ranges<I: IntoIterator<Item = Range<usize>>>(
+        size: usize,
+        ranges: I,
+    ) -> Result<SparseBitmap, RangeError> {
+        let mut sparse = SparseBitmap::new(size);
+        let mut previous_start = 0;
+
+        for (index, range) in ranges.into_iter().enumerate() {
+            if range.is_empty() {
+                continue;
+            }
+            if range.end > size {
+                return Err(RangeError::OutOfBounds { index });
+            }
+            if range.start < previous_start {
+                return Err(RangeError::Unsorted { index });
+            }
+
+            previous_start = range.start;
+            sparse.runs.push(Run::from_range((range.start, range.end)));
+        }
+
+        Ok(sparse)
+    }
+
+    /// Creates a `SparseBitmap` from ranges of 1s in any order. Ranges that overlap or
+    /// touch are merged, and empty ones are ignored.
+    ///
+    /// Fails with the index of the first range that ends beyond the size.
+    pub fn try_from_ranges<I: IntoIterator<Item = Range<usize>>>(
+        size: usize,
+        ranges: I,
+    ) -> Result<SparseBitmap, RangeError> {
+        let mut runs = Vec::new();
+
+        for (index, range) in ranges.into_iter().enumerate() {
+            if !range.is_empty() && range.end > size {
+                return Err(RangeError::OutOfBounds { index });
+            }
+
+            runs.push(Run::new(range.start, range.len()));
         }
+
+        let mut sparse = SparseBitmap::new(size);
+        sparse.rebuild(runs);
+        Ok(sparse)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: RunStore> SparseBitmap<S> {
+    /// Creates a new `SparseBitmap` with a fixed size, which keeps its runs in the
+    /// given empty store, e.g. `SparseBitmap::with_store(size, TreeRuns::default())`.
+    pub fn with_store(size: usize, runs: S) -> SparseBitmap<S> {
+        SparseBitmap { runs, size }
     }
 
     /// Get the bit value from a given position
     #[inline(always)]
     pub fn get(&self, position: usize) -> bool {
-        if position > self.size {
+        if position >= self.size {
             return false;
         }
 
-        for run in &self.runs {
-            if run.start <= position && run.end() > position {
-                return true;
-            }
-        }
-
-        false
+        self.runs
+            .floor(position)
+            .is_some_and(|run| run.end() > position)
     }
 
     /// Set a bit value in a given position
@@ -198,112 +541,229 @@ impl SparseBitmap {
             panic!("Index out of bounds");
         }
 
-        // Check if the position collides with existing runs
-        if let Some(index) = self
+        match value {
+            true => self.runs.insert(Run::new(position, 1)),
+            false => self.runs.remove(Run::new(position, 1)),
+        }
+    }
+
+    /// Sets a 1 to every position in the range. The runs it overlaps or touches are
+    /// merged with it into a single run.
+    pub fn insert_range(&mut self, range: Range<usize>) {
+        if range.end > self.size {
+            panic!("Index out of bounds");
+        }
+        if range.is_empty() {
+            return;
+        }
+
+        self.runs.insert(Run::from_range((range.start, range.end)));
+    }
+
+    /// Sets a 0 to every position in the range. The runs it overlaps are trimmed, split
+    /// around it, or removed when they lay entirely inside it.
+    pub fn remove_range(&mut self, range: Range<usize>) {
+        if range.end > self.size {
+            panic!("Index out of bounds");
+        }
+        if range.is_empty() {
+            return;
+        }
+
+        self.runs.remove(Run::from_range((range.start, range.end)));
+    }
+
+    /// Checks if every position in the range is set to 1. An empty range is always
+    /// contained.
+    pub fn contains_range(&self, range: Range<usize>) -> bool {
+        if range.is_empty() {
+            return true;
+        }
+
+        // Only the last run starting at or before the range can contain it
+        self.runs
+            .floor(range.start)
+            .is_some_and(|run| run.end() >= range.end)
+    }
+
+    /// Checks if any position in the range is set to 1.
+    pub fn intersects_range(&self, range: Range<usize>) -> bool {
+        if range.is_empty() {
+            return false;
+        }
+
+        // Only the last run starting before the range end can reach into it
+        self.runs
+            .floor(range.end - 1)
+            .is_some_and(|run| run.end() > range.start)
+    }
+
+    /// Iterate over the runs of consecutive 1s as ranges of positions, in ascending order
+    pub fn runs(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.ranges().map(|(start, end)| start..end)
+    }
+
+    /// Keeps only the runs for which `keep` returns true, setting a 0 to every
+    /// position of the other ones.
+    pub fn retain_runs<F: FnMut(&Range<usize>) -> bool>(&mut self, mut keep: F) {
+        self.runs.retain(|run| keep(&(run.start..run.end())));
+    }
+
+    /// Edits every run in place. The edited runs can overlap, touch, move past each
+    /// other or become empty: they are brought back to their canonical form
+    /// afterwards, and the 1s beyond the bitmap size are dropped.
+    pub fn update_runs<F: FnMut(&mut Range<usize>)>(&mut self, mut update: F) {
+        let runs = self
             .runs
             .iter()
-            .position(|run| run.start <= position && run.end() >= position)
-        {
-            match value {
-                true => self.set_one(position, index),
-                false => self.set_zero(position, index),
-            }
-        } else if value {
-            self.append(Run::new(position, 1));
-        }
+            .map(|run| {
+                let mut range = run.start..run.end();
+                update(&mut range);
+                Run::new(range.start, range.len())
+            })
+            .collect();
+
+        self.rebuild(runs);
     }
 
-    /// Set bit value to 1 for a given position in-between a run with the given index.
-    #[inline(always)]
-    fn set_one(&mut self, position: usize, index: usize) {
-        let current = self.runs.get(index).unwrap();
-
-        if position == current.start {
-            // Find the new start by merging any conflicts
-
-            let start = if current.start == 0 {
-                current.start
-            } else if let Some(index) = self
-                .runs
-                .iter()
-                .position(|run| run.end() == current.start - 1)
-            {
-                self.runs.remove(index).start
-            } else {
-                current.start - 1
-            };
+    /// Count the amount of 1s in the bitmap
+    pub fn len(&self) -> usize {
+        self.runs.ones()
+    }
 
-            let run = self.runs.get_mut(index).unwrap();
-            run.start = start
-        } else if position == current.end() {
-            // Find the new length by merging any conflicts
-            let length = if let Some(index) = self
-                .runs
-                .iter()
-                .position(|run| run.start == current.end() + 1)
-            {
-                self.runs.remove(index).length + 1
-            } else {
-                1
+    /// Checks if the bitmap has no 1s, whatever its size.
+    pub fn is_empty(&self) -> bool {
+        self.runs.count() == 0
+    }
+
+    /// Position of the first 1 in the bitmap
+    pub fn first(&self) -> Option<usize> {
+        self.runs.first().map(|run| run.start)
+    }
+
+    /// Position of the last 1 in the bitmap
+    pub fn last(&self) -> Option<usize> {
+        self.runs.last().map(|run| run.end() - 1)
+    }
+
+    /// Position of the `n`-th 1 in the bitmap, counting from 0
+    pub fn nth(&self, n: usize) -> Option<usize> {
+        self.runs.select(n)
+    }
+
+    /// Count the amount of 1s before a given position. Both stores keep count of the
+    /// 1s before their runs, so it takes `O(log n)`.
+    pub fn rank(&self, position: usize) -> usize {
+        self.runs.rank(position)
+    }
+
+    /// Position of the `k`-th 1 in the bitmap, counting from 0, which is the inverse of
+    /// `rank`: `rank(select(k))` is `k`.
+    pub fn select(&self, k: usize) -> Option<usize> {
+        self.runs.select(k)
+    }
+
+    /// Checks that the runs are in their canonical form: sorted by their start,
+    /// non-empty, within the bitmap size, and separated from each other by at least
+    /// one 0. Returns the first invariant that a run breaks.
+    ///
+    /// Every operation keeps the canonical form, and most of them rely on it.
+    pub fn validate(&self) -> Result<(), RunInvariant> {
+        let mut previous: Option<Run> = None;
+
+        for (index, run) in self.runs.iter().enumerate() {
+            if run.length == 0 {
+                return Err(RunInvariant::NonEmpty { index });
+            }
+            if run.end() > self.size {
+                return Err(RunInvariant::WithinSize { index });
+            }
+
+            let Some(previous) = previous.replace(run) else {
+                continue;
             };
 
-            let run = self.runs.get_mut(index).unwrap();
-            run.length += length;
+            if run.start < previous.start {
+                return Err(RunInvariant::Sorted { index });
+            }
+            if run.start < previous.end() {
+                return Err(RunInvariant::NonOverlapping { index });
+            }
+            if run.start == previous.end() {
+                return Err(RunInvariant::NonAdjacent { index });
+            }
         }
+
+        Ok(())
     }
 
-    /// Set bit value to 0 for a given position in-between a run with the given index.
-    #[inline(always)]
-    fn set_zero(&mut self, position: usize, index: usize) {
-        let run = self.runs.get_mut(index).unwrap();
+    /// Brings the runs to their canonical form: sorts them, merges the ones that
+    /// overlap or touch, and removes the empty ones and the 1s beyond the size.
+    pub fn normalize(&mut self) {
+        let runs = self.runs.iter().collect();
+        self.rebuild(runs);
+    }
 
-        if position == run.start {
-            run.start += 1
-        } else if position == run.end() {
-            run.length -= 1
-        } else {
-            // If a 0 is set in-between a run, create a new run with the leftover
-            let start = position + 1;
-            let end = run.end();
+    /// Replaces the runs with the canonical form of `runs`, which can be in any order.
+    fn rebuild(&mut self, mut runs: Vec<Run>) {
+        runs.sort_unstable_by_key(|run| run.start);
+
+        self.runs = S::default();
+        for run in runs {
+            let length = run.end().min(self.size).saturating_sub(run.start);
 
-            run.length = position - run.start;
-            if start < end - 1 {
-                self.runs.push(Run::new(start, end - start));
+            if length > 0 {
+                self.runs.push(Run::new(run.start, length));
             }
         }
     }
 
-    #[inline(always)]
-    fn append(&mut self, run: Run) {
-        if let Some((last, union)) = self
+    /// Removes the 1s beyond the bitmap size, which the operators take from the larger bitmap.
+    fn clear_padding(&mut self) {
+        self.runs.truncate(self.size);
+    }
+
+    /// Iterate over the ranges of 1s as `(start, end)` pairs, merging the runs that
+    /// touch each other and leaving out the positions beyond the bitmap size.
+    fn ranges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut runs = self
             .runs
-            .last_mut()
-            .and_then(|last| last.union(&run).map(|union| (last, union)))
-        {
-            *last = union;
-        } else {
-            self.runs.push(run);
-        }
+            .iter()
+            .map(|run| (run.start, run.end().min(self.size)))
+            .filter(|(start, end)| start < end)
+            .peekable();
+
+        core::iter::from_fn(move || {
+            let (start, mut end) = runs.next()?;
+
+            while let Some((_, next_end)) = runs.next_if(|(next_start, _)| *next_start <= end) {
+                end = end.max(next_end);
+            }
+
+            Some((start, end))
+        })
     }
 }
 
-impl BitAnd for &SparseBitmap {
-    type Output = SparseBitmap;
+#[cfg(feature = "alloc")]
+impl<S: RunStore> BitAnd for &SparseBitmap<S> {
+    type Output = SparseBitmap<S>;
 
     #[inline(always)]
     fn bitand(self, rhs: Self) -> Self::Output {
         let size = self.size.min(rhs.size);
-        let mut runs = Vec::new();
+        let mut sparse = SparseBitmap::with_store(size, S::default());
 
-        let mut iter = self.runs.iter();
-        let mut rhs_iter = rhs.runs.iter();
+        let mut iter = self.ranges().map(Run::from_range);
+        let mut rhs_iter = rhs.ranges().map(Run::from_range);
 
         let mut next = iter.next();
         let mut rhs_next = rhs_iter.next();
 
         while let (Some(run), Some(rhs_run)) = (next, rhs_next) {
-            if let Some(intersect) = run.intersect(rhs_run) {
-                runs.push(intersect);
+            // Runs that only touch each other intersect in an empty run
+            if let Some(intersect) = run.intersect(&rhs_run).filter(|run| run.length > 0) {
+                sparse.runs.push(intersect);
             }
 
             // Iterate to the next run by increasing the pointer of the
@@ -316,63 +776,58 @@ impl BitAnd for &SparseBitmap {
             }
         }
 
-        SparseBitmap { runs, size }
+        sparse.clear_padding();
+        sparse
     }
 }
 
-impl BitOr for &SparseBitmap {
-    type Output = SparseBitmap;
+#[cfg(feature = "alloc")]
+impl<S: RunStore> BitOr for &SparseBitmap<S> {
+    type Output = SparseBitmap<S>;
 
     #[inline(always)]
     fn bitor(self, rhs: Self) -> Self::Output {
         let size = self.size.min(rhs.size);
-        let mut sparse = SparseBitmap::new(size);
-
-        let mut iter = self.runs.iter();
-        let mut rhs_iter = rhs.runs.iter();
-
-        let mut next = iter.next();
-        let mut rhs_next = rhs_iter.next();
-
-        // Iterate through both bitmaps and append all runs together
-        loop {
-            match (next, rhs_next) {
-                (Some(run), Some(rhs_run)) => {
-                    sparse.append(*run);
-                    sparse.append(*rhs_run);
-                    next = iter.next();
-                    rhs_next = rhs_iter.next();
-                }
-                (Some(run), None) => {
-                    sparse.append(*run);
-                    next = iter.next();
-                }
-                (None, Some(run)) => {
-                    sparse.append(*run);
-                    rhs_next = rhs_iter.next();
-                }
-                (None, None) => break,
-            }
+        let mut sparse = SparseBitmap::with_store(size, S::default());
+
+        let mut iter = self.ranges().map(Run::from_range).peekable();
+        let mut rhs_iter = rhs.ranges().map(Run::from_range).peekable();
+
+        // Append the runs of both bitmaps ordered by their start, so that every run
+        // is merged with the previous one when they overlap or touch.
+        while let Some(run) = match (iter.peek(), rhs_iter.peek()) {
+            (Some(run), Some(rhs_run)) if rhs_run.start < run.start => rhs_iter.next(),
+            (Some(_), _) => iter.next(),
+            (None, _) => rhs_iter.next(),
+        } {
+            sparse.runs.push(run);
         }
 
+        sparse.clear_padding();
         sparse
     }
 }
 
-impl Not for &SparseBitmap {
-    type Output = SparseBitmap;
+#[cfg(feature = "alloc")]
+impl<S: RunStore> Not for &SparseBitmap<S> {
+    type Output = SparseBitmap<S>;
 
     #[inline(always)]
     fn not(self) -> Self::Output {
         let mut start: usize = 0;
-        let mut runs = Vec::new();
+        let mut runs = S::default();
 
-        for run in &self.runs {
-            if start < run.start {
-                runs.push(Run::new(start, run.start - start));
+        for (run_start, run_end) in self.ranges() {
+            if start < run_start {
+                runs.push(Run::new(start, run_start - start));
             }
 
-            start = run.end();
+            start = run_end;
+        }
+
+        // The 0s after the last run up to the size
+        if start < self.size {
+            runs.push(Run::new(start, self.size - start));
         }
 
         SparseBitmap {
@@ -382,105 +837,59 @@ impl Not for &SparseBitmap {
     }
 }
 
-impl BitXor for &SparseBitmap {
-    type Output = SparseBitmap;
+#[cfg(feature = "alloc")]
+impl<S: RunStore> BitXor for &SparseBitmap<S> {
+    type Output = SparseBitmap<S>;
 
     #[inline(always)]
     fn bitxor(self, rhs: Self) -> Self::Output {
         let size = self.size.min(rhs.size);
-        let mut bitmap = SparseBitmap::new(size);
-
-        let mut iter = self.runs.iter();
-        let mut rhs_iter = rhs.runs.iter();
-
-        let mut next = iter.next();
-        let mut rhs_next = rhs_iter.next();
+        let mut sparse = SparseBitmap::with_store(size, S::default());
+
+        // Every start and end of a run flips the bits from its position onwards. Both
+        // bitmaps flipping at the same position cancel out, so the result only flips
+        // where a single bitmap does.
+        fn boundaries<S: RunStore>(
+            bitmap: &SparseBitmap<S>,
+            size: usize,
+        ) -> impl Iterator<Item = usize> + '_ {
+            bitmap
+                .ranges()
+                .flat_map(|(start, end)| [start, end])
+                .map(move |position| position.min(size))
+        }
+        let mut iter = boundaries(self, size).peekable();
+        let mut rhs_iter = boundaries(rhs, size).peekable();
 
-        let mut previous_end = None;
+        let mut start = None;
 
         loop {
-            match (next, rhs_next) {
-                // If both there are runs for both bitmaps
-                (Some(run), Some(rhs_run)) => {
-                    let start = run.start;
-                    let end = run.end();
-
-                    let rhs_start = rhs_run.start;
-                    let rhs_end = rhs_run.end();
-
-                    // New start is the minimum of both runs start or the previous run end point,
-                    // in case it exists and is between both run start points.
-                    let mut new_start = run.start.min(rhs_run.start);
-
-                    if let Some(previous_end) = previous_end {
-                        // If previous end point is between the both runs start points.
-                        if (previous_end > start && previous_end < rhs_start)
-                            || (previous_end > rhs_start && previous_end < start)
-                        {
-                            new_start = previous_end;
-                        }
-                    }
-
-                    // New end is the maximum start point or the minimum end of current runs.
-                    let new_end = start.max(rhs_start).min(end).min(rhs_end);
-
-                    let length = new_end - new_start;
-
-                    if length > 0 {
-                        bitmap.append(Run::new(new_start, length));
-                    }
-
-                    // Iterate to the next run by increasing the pointer of the
-                    // run with smallest end, so that we cover that a single long
-                    // run has multiple intersections.
-                    if end < rhs_end {
-                        next = iter.next();
-                        previous_end = Some(end);
-                    } else {
-                        rhs_next = rhs_iter.next();
-                        previous_end = Some(rhs_end);
-                    }
-                }
-                // If only left bitmap has runs left
-                (Some(run), None) => {
-                    let end = run.end();
-
-                    // If previous end exists and it's smaller than current end.
-                    if let Some(previous_end) = previous_end {
-                        if previous_end < end {
-                            bitmap.append(Run::new(previous_end, end - previous_end));
-                        }
-                    } else {
-                        bitmap.append(*run);
-                    }
-
-                    next = iter.next();
-                    previous_end = Some(end);
-                }
-                // If only right bitmap has runs left
-                (None, Some(rhs_run)) => {
-                    let rhs_end = rhs_run.end();
-
-                    // If previous end exists and it's smaller than current end.
-                    if let Some(previous_end) = previous_end {
-                        if previous_end < rhs_end {
-                            bitmap.append(Run::new(previous_end, rhs_end - previous_end));
-                        }
-                    } else {
-                        bitmap.append(*rhs_run);
-                    }
-
-                    rhs_next = rhs_iter.next();
-                    previous_end = Some(rhs_end);
+            let position = match (iter.peek(), rhs_iter.peek()) {
+                (Some(position), Some(rhs_position)) if position == rhs_position => {
+                    iter.next();
+                    rhs_iter.next();
+                    continue;
                 }
+                (Some(position), Some(rhs_position)) if rhs_position < position => rhs_iter.next(),
+                (Some(_), _) => iter.next(),
+                (None, Some(_)) => rhs_iter.next(),
                 (None, None) => break,
+            };
+
+            match (start.take(), position) {
+                (None, Some(position)) => start = Some(position),
+                (Some(start), Some(end)) if start < end => {
+                    sparse.runs.push(Run::new(start, end - start))
+                }
+                _ => {}
             }
         }
 
-        bitmap
+        sparse
     }
 }
 
+#[cfg(feature = "alloc")]
 impl From<&str> for SparseBitmap {
     fn from(value: &str) -> Self {
         let size = value.len();
@@ -505,18 +914,37 @@ impl From<&str> for SparseBitmap {
             runs.push(Run::new(start, size - start));
         }
 
-        SparseBitmap { runs, size }
+        SparseBitmap {
+            runs: VecRuns::from(runs),
+            size,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&Bitmap> for SparseBitmap {
+    fn from(bitmap: &Bitmap) -> Self {
+        SparseBitmap {
+            runs: VecRuns::from(
+                bitmap
+                    .runs()
+                    .map(|run| Run::from_range((run.start, run.end)))
+                    .collect::<Vec<_>>(),
+            ),
+            size: bitmap.size,
+        }
     }
 }
 
-impl ToString for SparseBitmap {
-    fn to_string(&self) -> String {
+#[cfg(feature = "alloc")]
+impl<S: RunStore> fmt::Display for SparseBitmap<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut result = (0..self.size).fold(String::with_capacity(self.size), |mut acc, _| {
             acc.push('0');
             acc
         });
 
-        for run in &self.runs {
+        for run in self.runs.iter() {
             let ones = (0..run.length).fold(String::with_capacity(run.length), |mut acc, _| {
                 acc.push('1');
                 acc
@@ -527,80 +955,386 @@ impl ToString for SparseBitmap {
             result.replace_range(start..end, &ones)
         }
 
-        result
+        f.write_str(&result)
     }
 }
 
-// Run represents a range in a `SparseBitmap`, where 1s are stored
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Run {
-    start: usize,
-    length: usize,
+/// Bitmaps are equal when they have the same size and bits, regardless of the bits
+/// beyond the size that the operators may leave in the last chunk.
+#[cfg(feature = "alloc")]
+impl PartialEq for Bitmap {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.masked_chunks().eq(other.masked_chunks())
+    }
 }
 
-impl Run {
-    fn new(start: usize, length: usize) -> Run {
-        Run { start, length }
+#[cfg(feature = "alloc")]
+impl Eq for Bitmap {}
+
+#[cfg(feature = "alloc")]
+impl Hash for Bitmap {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.size.hash(state);
+        self.masked_chunks().for_each(|chunk| chunk.hash(state));
     }
+}
 
-    #[inline(always)]
-    fn end(&self) -> usize {
-        self.start + self.length
+/// Bitmaps are ordered as sequences of bits from the lowest position, as a `Vec<bool>`:
+/// the first differing position decides, and a bitmap that is a prefix of the other
+/// one is smaller.
+#[cfg(feature = "alloc")]
+impl Ord for Bitmap {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let size = self.size.min(other.size);
+
+        for (index, (chunk, other_chunk)) in self.chunks.iter().zip(&other.chunks).enumerate() {
+            let mask = low_bits_mask(size.saturating_sub(index * usize::BITS as usize));
+            let difference = (chunk ^ other_chunk) & mask;
+
+            if difference != 0 {
+                let bit = 1 << difference.trailing_zeros();
+                return if chunk & bit != 0 {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                };
+            }
+        }
+
+        self.size.cmp(&other.size)
     }
+}
 
-    #[inline(always)]
-    fn intersect(&self, run: &Run) -> Option<Run> {
-        if self.matches(run) {
-            let start = self.start.max(run.start);
-            let end = self.end().min(run.end());
+#[cfg(feature = "alloc")]
+impl PartialOrd for Bitmap {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-            Some(Run::new(start, end - start))
-        } else {
-            None
+/// Sets a 1 in every position, growing the bitmap when a position is beyond its size.
+#[cfg(feature = "alloc")]
+impl Extend<usize> for Bitmap {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, positions: I) {
+        for position in positions {
+            if position >= self.size {
+                self.resize(position + 1);
+            }
+            self.set(position, true);
         }
     }
+}
 
-    #[inline(always)]
-    fn union(&self, run: &Run) -> Option<Run> {
-        if self.matches(run) {
-            let start = self.start.min(run.start);
-            let end = self.end().max(run.end());
+#[cfg(feature = "alloc")]
+impl Index<usize> for Bitmap {
+    type Output = bool;
 
-            Some(Run::new(start, end - start))
+    fn index(&self, position: usize) -> &Self::Output {
+        if position >= self.size {
+            panic!("Index out of bounds");
+        }
+
+        if self.get(position) {
+            &true
         } else {
-            None
+            &false
         }
     }
+}
 
-    #[inline(always)]
-    fn matches(&self, run: &Run) -> bool {
-        self.contains(run.start) || run.contains(self.start)
+/// Sparse bitmaps are equal when they have the same size and bits, regardless of how
+/// their 1s are split in runs.
+#[cfg(feature = "alloc")]
+impl<S: RunStore> PartialEq for SparseBitmap<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.ranges().eq(other.ranges())
     }
+}
 
-    #[inline(always)]
-    fn contains(&self, index: usize) -> bool {
-        index >= self.start && index <= self.end()
+#[cfg(feature = "alloc")]
+impl<S: RunStore> Eq for SparseBitmap<S> {}
+
+#[cfg(feature = "alloc")]
+impl<S: RunStore> Hash for SparseBitmap<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.size.hash(state);
+        self.ranges().for_each(|range| range.hash(state));
     }
 }
 
-/// Calculate the amount of chunks needed for the desired bitmap size, and the bits per chunk.
-#[inline(always)]
-fn chunks_count(size: usize, chunk_bit_size: usize) -> usize {
-    (size + chunk_bit_size - 1) / chunk_bit_size
-}
+/// Sparse bitmaps are ordered as `Bitmap`s, comparing their ranges of 1s instead of
+/// every bit.
+#[cfg(feature = "alloc")]
+impl<S: RunStore> Ord for SparseBitmap<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let size = self.size.min(other.size);
+        let clip = |(start, end): (usize, usize)| (start, end.min(size));
 
-/// Calculate the bit index in the chunks by a given position, and chunk bit size.
-#[inline(always)]
-fn bit_index(position: usize, chunk_bit_size: usize) -> (usize, usize) {
-    let chunk_index = position / chunk_bit_size;
-    let bit_index_in_chunk = position % chunk_bit_size;
+        let mut ranges = self.ranges().map(clip).filter(|(start, end)| start < end);
+        let mut other_ranges = other.ranges().map(clip).filter(|(start, end)| start < end);
 
-    (chunk_index, bit_index_in_chunk)
-}
+        loop {
+            let ordering = match (ranges.next(), other_ranges.next()) {
+                (None, None) => return self.size.cmp(&other.size),
+                (Some(_), None) => Ordering::Greater,
+                (None, Some(_)) => Ordering::Less,
+                // The range that starts first has a 1 where the other bitmap has a 0,
+                // and so does the range that ends last
+                (Some((start, end)), Some((other_start, other_end))) => {
+                    other_start.cmp(&start).then(end.cmp(&other_end))
+                }
+            };
 
-#[cfg(test)]
-mod tests {
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: RunStore> PartialOrd for SparseBitmap<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Sets a 1 in every position of the ranges, in any order. The size is the end of the
+/// last range.
+#[cfg(feature = "alloc")]
+impl<S: RunStore> FromIterator<Range<usize>> for SparseBitmap<S> {
+    fn from_iter<I: IntoIterator<Item = Range<usize>>>(ranges: I) -> Self {
+        let mut sparse = SparseBitmap::with_store(0, S::default());
+        let mut runs = Vec::new();
+
+        for range in ranges {
+            if !range.is_empty() {
+                sparse.size = sparse.size.max(range.end);
+            }
+
+            runs.push(Run::new(range.start, range.len()));
+        }
+
+        sparse.rebuild(runs);
+        sparse
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Vec<Range<usize>>> for SparseBitmap {
+    fn from(ranges: Vec<Range<usize>>) -> Self {
+        ranges.into_iter().collect()
+    }
+}
+
+/// Sets a 1 in every position, growing the bitmap when a position is beyond its size.
+#[cfg(feature = "alloc")]
+impl<S: RunStore> Extend<usize> for SparseBitmap<S> {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, positions: I) {
+        for position in positions {
+            self.size = self.size.max(position + 1);
+            self.set(position, true);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: RunStore> Index<usize> for SparseBitmap<S> {
+    type Output = bool;
+
+    fn index(&self, position: usize) -> &Self::Output {
+        if position >= self.size {
+            panic!("Index out of bounds");
+        }
+
+        if self.get(position) {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+/// Implements `Sum` as the union and `Product` as the intersection of many bitmaps.
+/// As with the operators, the result has the size of the smallest bitmap, and folding
+/// no bitmaps returns an empty one.
+macro_rules! impl_fold {
+    (<$($generic:ident: $bound:ident),*> $bitmap:ty) => {
+        #[cfg(feature = "alloc")]
+        impl<$($generic: $bound),*> Sum for $bitmap {
+            fn sum<I: Iterator<Item = $bitmap>>(iter: I) -> Self {
+                iter.reduce(|acc, bitmap| &acc | &bitmap)
+                    .unwrap_or_default()
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl<'a, $($generic: $bound),*> Sum<&'a $bitmap> for $bitmap {
+            fn sum<I: Iterator<Item = &'a $bitmap>>(mut iter: I) -> Self {
+                let first = iter.next().cloned().unwrap_or_default();
+                iter.fold(first, |acc, bitmap| &acc | bitmap)
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl<$($generic: $bound),*> Product for $bitmap {
+            fn product<I: Iterator<Item = $bitmap>>(iter: I) -> Self {
+                iter.reduce(|acc, bitmap| &acc & &bitmap)
+                    .unwrap_or_default()
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl<'a, $($generic: $bound),*> Product<&'a $bitmap> for $bitmap {
+            fn product<I: Iterator<Item = &'a $bitmap>>(mut iter: I) -> Self {
+                let first = iter.next().cloned().unwrap_or_default();
+                iter.fold(first, |acc, bitmap| &acc & bitmap)
+            }
+        }
+    };
+}
+
+impl_fold!(<> Bitmap);
+impl_fold!(<S: RunStore> SparseBitmap<S>);
+
+#[cfg(feature = "alloc")]
+/// RunInvariant is an invariant of the canonical runs of a `SparseBitmap`, broken by
+/// the run at `index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunInvariant {
+    /// Every run has at least one position
+    NonEmpty { index: usize },
+    /// Every run ends before the bitmap size
+    WithinSize { index: usize },
+    /// Runs are sorted by their start
+    Sorted { index: usize },
+    /// Runs do not share any position
+    NonOverlapping { index: usize },
+    /// Runs are separated by at least one 0, otherwise they would be a single run
+    NonAdjacent { index: usize },
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for RunInvariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunInvariant::NonEmpty { index } => write!(f, "Run {index} is empty"),
+            RunInvariant::WithinSize { index } => write!(f, "Run {index} exceeds the size"),
+            RunInvariant::Sorted { index } => {
+                write!(f, "Run {index} starts before the previous one")
+            }
+            RunInvariant::NonOverlapping { index } => {
+                write!(f, "Run {index} overlaps the previous one")
+            }
+            RunInvariant::NonAdjacent { index } => {
+                write!(f, "Run {index} touches the previous one")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RunInvariant {}
+
+#[cfg(feature = "alloc")]
+/// RangeError is why a `SparseBitmap` can not be built from ranges of 1s, caused by
+/// the range at `index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    /// The range starts before the previous one, where ranges must be sorted
+    Unsorted { index: usize },
+    /// The range ends beyond the bitmap size
+    OutOfBounds { index: usize },
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeError::Unsorted { index } => {
+                write!(f, "Range {index} starts before the previous one")
+            }
+            RangeError::OutOfBounds { index } => {
+                write!(f, "Range {index} ends beyond the size")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RangeError {}
+
+/// Calculate the amount of chunks needed for the desired bitmap size, and the bits per chunk.
+#[inline(always)]
+fn chunks_count(size: usize, chunk_bit_size: usize) -> usize {
+    size.div_ceil(chunk_bit_size)
+}
+
+/// Calculate a chunk with the lowest `count` bits set to 1. Counts larger than the
+/// chunk bit size return a chunk full of 1s.
+#[inline(always)]
+fn low_bits_mask(count: usize) -> usize {
+    if count >= usize::BITS as usize {
+        usize::MAX
+    } else {
+        (1 << count) - 1
+    }
+}
+
+/// Iterate over the runs of consecutive 1s of a chunk, offset by the positions of the
+/// chunks before it.
+#[cfg(feature = "alloc")]
+fn runs_in_chunk(index: usize, mut chunk: usize) -> impl Iterator<Item = Range<usize>> {
+    let offset = index * usize::BITS as usize;
+
+    // Consume the chunk run by run: skip the 0s until the next 1, and count the 1s
+    // that follow
+    core::iter::from_fn(move || {
+        if chunk == 0 {
+            return None;
+        }
+
+        let start = chunk.trailing_zeros() as usize;
+        let length = (chunk >> start).trailing_ones() as usize;
+        chunk &= !low_bits_mask(start + length);
+
+        Some(offset + start..offset + start + length)
+    })
+}
+
+/// Iterate over the positions of the 1s of a chunk, offset by the positions of the
+/// chunks before it.
+fn ones_in_chunk(index: usize, mut chunk: usize) -> impl Iterator<Item = usize> {
+    let offset = index * usize::BITS as usize;
+
+    // Pop the lowest 1 of the chunk until there are none left
+    core::iter::from_fn(move || {
+        if chunk == 0 {
+            return None;
+        }
+
+        let bit = chunk.trailing_zeros() as usize;
+        chunk &= chunk - 1;
+
+        Some(offset + bit)
+    })
+}
+
+/// Calculate the bit index in the chunks by a given position, and chunk bit size.
+#[inline(always)]
+fn bit_index(position: usize, chunk_bit_size: usize) -> (usize, usize) {
+    let chunk_index = position / chunk_bit_size;
+    let bit_index_in_chunk = position % chunk_bit_size;
+
+    (chunk_index, bit_index_in_chunk)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
     use super::*;
+    use crate::test_util::{bitmap, sparse, to_string};
+    use alloc::string::ToString;
+    use proptest::prelude::*;
 
     #[test]
     fn test_bitmap_chunks_of_size() {
@@ -615,13 +1349,13 @@ mod tests {
     fn test_bitmap_get() {
         let bitmap = Bitmap::from("11001");
 
-        assert_eq!(bitmap.get(0), true);
-        assert_eq!(bitmap.get(1), false);
-        assert_eq!(bitmap.get(2), false);
-        assert_eq!(bitmap.get(3), true);
-        assert_eq!(bitmap.get(4), true);
-        assert_eq!(bitmap.get(5), false);
-        assert_eq!(bitmap.get(6), false);
+        assert!(bitmap.get(0));
+        assert!(!bitmap.get(1));
+        assert!(!bitmap.get(2));
+        assert!(bitmap.get(3));
+        assert!(bitmap.get(4));
+        assert!(!bitmap.get(5));
+        assert!(!bitmap.get(6));
     }
 
     #[test]
@@ -728,7 +1462,7 @@ mod tests {
         bitmap.set(3, true);
         bitmap.set(1, true);
 
-        assert_eq!(bitmap.runs, vec![Run::new(0, 4)]);
+        assert_eq!(*bitmap.runs, [Run::new(0, 4)]);
         assert_eq!(bitmap, SparseBitmap::from("01111"));
     }
 
@@ -745,10 +1479,281 @@ mod tests {
 
         bitmap.set(2, false);
 
-        assert_eq!(bitmap.runs, vec![Run::new(0, 2), Run::new(3, 2)]);
+        assert_eq!(*bitmap.runs, [Run::new(0, 2), Run::new(3, 2)]);
         assert_eq!(bitmap, SparseBitmap::from("11011"));
     }
 
+    #[test]
+    fn test_set_keeps_canonical_runs_sparse() {
+        let mut bitmap = SparseBitmap::from("0111011111");
+
+        // Splitting a run keeps the right side after it, even if it has a single 1
+        bitmap.set(3, false);
+        assert_eq!(
+            *bitmap.runs,
+            [Run::new(0, 3), Run::new(4, 1), Run::new(6, 3)]
+        );
+
+        // Clearing the only 1 of a run removes it
+        bitmap.set(4, false);
+        assert_eq!(*bitmap.runs, [Run::new(0, 3), Run::new(6, 3)]);
+
+        // Filling the gap between two runs merges them
+        bitmap.set(3, true);
+        bitmap.set(5, true);
+        bitmap.set(4, true);
+        assert_eq!(*bitmap.runs, [Run::new(0, 9)]);
+        assert_eq!(bitmap.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_insert_range_sparse() {
+        let mut bitmap = SparseBitmap::from("0011001100110000");
+
+        // Merges the run it overlaps and the one starting right after it
+        bitmap.insert_range(3..8);
+        assert_eq!(*bitmap.runs, [Run::new(3, 7), Run::new(12, 2)]);
+
+        // A range between runs becomes a new run
+        bitmap.insert_range(0..2);
+        assert_eq!(
+            *bitmap.runs,
+            [Run::new(0, 2), Run::new(3, 7), Run::new(12, 2)]
+        );
+
+        // Filling the gaps merges everything into a single run
+        bitmap.insert_range(2..13);
+        assert_eq!(*bitmap.runs, [Run::new(0, 14)]);
+
+        bitmap.insert_range(5..5);
+        assert_eq!(*bitmap.runs, [Run::new(0, 14)]);
+        assert_eq!(bitmap.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_remove_range_sparse() {
+        let mut bitmap = SparseBitmap::from("0111111111110111");
+        assert_eq!(*bitmap.runs, [Run::new(0, 3), Run::new(4, 11)]);
+
+        // Splits the run it falls into
+        bitmap.remove_range(6..8);
+        assert_eq!(
+            *bitmap.runs,
+            [Run::new(0, 3), Run::new(4, 2), Run::new(8, 7)]
+        );
+
+        // Trims the runs at both ends and removes the one inside
+        bitmap.remove_range(1..10);
+        assert_eq!(*bitmap.runs, [Run::new(0, 1), Run::new(10, 5)]);
+
+        bitmap.remove_range(0..16);
+        assert!(bitmap.runs.is_empty());
+    }
+
+    #[test]
+    fn test_range_queries_sparse() {
+        let bitmap = SparseBitmap::from("0111100110");
+
+        assert!(bitmap.contains_range(1..3));
+        assert!(bitmap.contains_range(5..9));
+        assert!(!bitmap.contains_range(1..4));
+        assert!(!bitmap.contains_range(4..6));
+        assert!(bitmap.contains_range(3..3));
+
+        assert!(bitmap.intersects_range(0..2));
+        assert!(bitmap.intersects_range(3..6));
+        assert!(!bitmap.intersects_range(3..5));
+        assert!(!bitmap.intersects_range(9..10));
+        assert!(!bitmap.intersects_range(1..1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_insert_range_out_of_bounds_sparse() {
+        SparseBitmap::new(5).insert_range(3..6);
+    }
+
+    #[test]
+    fn test_rank_select_sparse() {
+        let mut bitmap = SparseBitmap::from("0111000110");
+
+        assert_eq!(bitmap.len(), 5);
+        assert_eq!((bitmap.first(), bitmap.last()), (Some(1), Some(8)));
+        assert_eq!(bitmap.nth(2), Some(6));
+        assert_eq!(bitmap.nth(5), None);
+
+        for (position, rank) in [(0, 0), (1, 0), (2, 1), (3, 2), (6, 2), (7, 3), (10, 5)] {
+            assert_eq!(bitmap.rank(position), rank);
+        }
+        for (k, position) in [
+            (0, Some(1)),
+            (1, Some(2)),
+            (2, Some(6)),
+            (4, Some(8)),
+            (5, None),
+        ] {
+            assert_eq!(bitmap.select(k), position);
+        }
+
+        // The 1s before the runs are counted again after every change
+        bitmap.insert_range(0..2);
+        bitmap.remove_range(1..2);
+        assert_eq!(bitmap.to_string(), "0111000101");
+        assert_eq!(
+            (bitmap.len(), bitmap.rank(7), bitmap.select(3)),
+            (5, 3, Some(7))
+        );
+        bitmap.retain_runs(|run| run.start > 0);
+        assert_eq!(
+            (bitmap.len(), bitmap.rank(7), bitmap.select(0)),
+            (4, 2, Some(2))
+        );
+
+        let empty = SparseBitmap::new(10);
+        assert!(empty.is_empty());
+        assert_eq!((empty.first(), empty.last()), (None, None));
+        assert_eq!((empty.len(), empty.select(0)), (0, None));
+    }
+
+    #[test]
+    fn test_runs_sparse() {
+        let mut bitmap = SparseBitmap::from("0111000110");
+        assert_eq!(bitmap.runs().collect::<Vec<_>>(), vec![1..3, 6..9]);
+
+        bitmap.retain_runs(|run| run.len() > 2);
+        assert_eq!(bitmap.runs().collect::<Vec<_>>(), vec![6..9]);
+
+        bitmap.insert_range(0..2);
+        bitmap.update_runs(|run| {
+            run.start += 1;
+            run.end += 2;
+        });
+        assert_eq!(bitmap.runs().collect::<Vec<_>>(), vec![1..4, 7..10]);
+
+        // Runs that end up touching are merged, and empty ones are removed
+        bitmap.update_runs(|run| {
+            if run.start == 1 {
+                run.end = 7;
+            } else {
+                run.end = run.start;
+            }
+        });
+        assert_eq!(bitmap.runs().collect::<Vec<_>>(), vec![1..7]);
+        assert_eq!(bitmap.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_runs_bitmap() {
+        let mut bitmap = Bitmap::new(200);
+        bitmap.set(0, true);
+        bitmap.set(1, true);
+        for position in 60..130 {
+            bitmap.set(position, true);
+        }
+        bitmap.set(199, true);
+
+        assert_eq!(
+            bitmap.runs().collect::<Vec<_>>(),
+            vec![0..2, 60..130, 199..200]
+        );
+        assert_eq!(Bitmap::new(0).runs().count(), 0);
+    }
+
+    #[test]
+    fn test_sparse_from_ranges() {
+        let bitmap = SparseBitmap::from(vec![6..8, 1..3, 2..4, 8..9, 5..5]);
+        assert_eq!(*bitmap.runs, [Run::new(1, 3), Run::new(6, 3)]);
+        assert_eq!(bitmap.size, 9);
+
+        let bitmap = (0..3).map(|i| i * 4..i * 4 + 2).collect::<SparseBitmap>();
+        assert_eq!(bitmap.to_string(), "1100110011");
+
+        assert_eq!(
+            SparseBitmap::try_from_ranges(12, [6..8, 1..3, 2..4, 8..9]),
+            Ok(SparseBitmap::from("000111001110"))
+        );
+        assert_eq!(
+            SparseBitmap::try_from_ranges(8, [1..3, 6..9]),
+            Err(RangeError::OutOfBounds { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_sparse_from_sorted_ranges() {
+        let bitmap = SparseBitmap::from_sorted_ranges(12, [1..3, 2..4, 4..5, 7..7, 8..10]).unwrap();
+        assert_eq!(*bitmap.runs, [Run::new(1, 4), Run::new(8, 2)]);
+        assert_eq!(bitmap.validate(), Ok(()));
+
+        assert_eq!(
+            SparseBitmap::from_sorted_ranges(12, [1..3, 8..10, 4..5]),
+            Err(RangeError::Unsorted { index: 2 })
+        );
+        assert_eq!(
+            SparseBitmap::from_sorted_ranges(12, [1..3, 8..13]),
+            Err(RangeError::OutOfBounds { index: 1 })
+        );
+        assert_eq!(
+            RangeError::Unsorted { index: 2 }.to_string(),
+            "Range 2 starts before the previous one"
+        );
+    }
+
+    #[test]
+    fn test_validate_sparse() {
+        let mut bitmap = SparseBitmap::new(10);
+
+        let cases = [
+            (
+                vec![Run::new(1, 2), Run::new(4, 0)],
+                RunInvariant::NonEmpty { index: 1 },
+            ),
+            (vec![Run::new(8, 3)], RunInvariant::WithinSize { index: 0 }),
+            (
+                vec![Run::new(5, 1), Run::new(1, 1)],
+                RunInvariant::Sorted { index: 1 },
+            ),
+            (
+                vec![Run::new(1, 3), Run::new(2, 3)],
+                RunInvariant::NonOverlapping { index: 1 },
+            ),
+            (
+                vec![Run::new(1, 3), Run::new(4, 1)],
+                RunInvariant::NonAdjacent { index: 1 },
+            ),
+        ];
+
+        for (runs, invariant) in cases {
+            bitmap.runs = runs.into();
+            assert_eq!(bitmap.validate(), Err(invariant));
+        }
+
+        bitmap.runs = vec![Run::new(0, 1), Run::new(2, 8)].into();
+        assert_eq!(bitmap.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_normalize_sparse() {
+        let mut bitmap = SparseBitmap::new(12);
+        bitmap.runs = vec![
+            Run::new(6, 2),
+            Run::new(0, 0),
+            Run::new(1, 3),
+            Run::new(2, 1),
+            Run::new(4, 1),
+            Run::new(10, 5),
+        ]
+        .into();
+
+        bitmap.normalize();
+
+        assert_eq!(
+            *bitmap.runs,
+            [Run::new(1, 4), Run::new(6, 2), Run::new(10, 2)]
+        );
+        assert_eq!(bitmap.validate(), Ok(()));
+        assert_eq!(bitmap, SparseBitmap::from("110011011110"));
+    }
+
     #[test]
     fn test_set_add_zero_empty_runs_sparse() {
         let mut bitmap = SparseBitmap::new(5);
@@ -758,7 +1763,7 @@ mod tests {
         bitmap.set(2, false);
         bitmap.set(3, false);
 
-        assert_eq!(bitmap.runs, vec![]);
+        assert!(bitmap.runs.is_empty());
         assert_eq!(bitmap, SparseBitmap::from("00000"));
     }
 
@@ -766,13 +1771,13 @@ mod tests {
     fn test_get_sparse() {
         let bitmap = SparseBitmap::from("11001");
 
-        assert_eq!(bitmap.get(0), true);
-        assert_eq!(bitmap.get(1), false);
-        assert_eq!(bitmap.get(2), false);
-        assert_eq!(bitmap.get(3), true);
-        assert_eq!(bitmap.get(4), true);
-        assert_eq!(bitmap.get(5), false);
-        assert_eq!(bitmap.get(6), false);
+        assert!(bitmap.get(0));
+        assert!(!bitmap.get(1));
+        assert!(!bitmap.get(2));
+        assert!(bitmap.get(3));
+        assert!(bitmap.get(4));
+        assert!(!bitmap.get(5));
+        assert!(!bitmap.get(6));
     }
 
     #[test]
@@ -824,6 +1829,22 @@ mod tests {
         assert_eq!(!&SparseBitmap::from("11000"), SparseBitmap::from("00111"));
         assert_eq!(!&SparseBitmap::from("11011"), SparseBitmap::from("00100"));
         assert_eq!(!&SparseBitmap::from("11111"), SparseBitmap::from("00000"));
+        assert_eq!(!&SparseBitmap::from("00110"), SparseBitmap::from("11001"));
+        assert_eq!(!&SparseBitmap::new(3), SparseBitmap::from("111"));
+    }
+
+    #[test]
+    fn test_sparse_operators_clip_to_smallest_size() {
+        let small = SparseBitmap::from("01");
+        let large = SparseBitmap::from("1110");
+
+        assert_eq!(&small | &large, SparseBitmap::from("11"));
+        assert_eq!(
+            &large & &SparseBitmap::from("111"),
+            SparseBitmap::from("110")
+        );
+        assert_eq!(&small ^ &large, SparseBitmap::from("11"));
+        assert_eq!((&small | &large).to_string(), "11");
     }
 
     #[test]
@@ -857,4 +1878,278 @@ mod tests {
             SparseBitmap::from("11111")
         );
     }
+
+    #[test]
+    fn test_bitmap_resize() {
+        let mut bitmap = !&Bitmap::from("00100");
+
+        bitmap.resize(8);
+        assert_eq!(bitmap, Bitmap::from("00011011"));
+
+        bitmap.resize(3);
+        assert_eq!(bitmap, Bitmap::from("011"));
+
+        bitmap.resize(200);
+        assert_eq!(bitmap.count_ones(), 2);
+    }
+
+    #[test]
+    fn test_bitmap_bytes() {
+        let bitmap = !&Bitmap::from("0000000001");
+
+        assert_eq!(
+            bitmap.to_bytes(),
+            vec![10, 0, 0, 0, 0, 0, 0, 0, 0b11111110, 0b11]
+        );
+        assert_eq!(
+            Bitmap::from_bytes(&bitmap.to_bytes()),
+            Some(Bitmap::from("1111111110"))
+        );
+        assert_eq!(Bitmap::from_bytes(&[10, 0, 0, 0, 0, 0, 0, 0, 0xFF]), None);
+        assert_eq!(Bitmap::from_bytes(&[0, 0, 0]), None);
+        assert_eq!(
+            Bitmap::from_bytes(&Bitmap::new(0).to_bytes()),
+            Some(Bitmap::new(0))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_bitmap_read_write() {
+        let (first, second) = (Bitmap::from("1011"), Bitmap::from("110000000001"));
+
+        let mut bytes = Vec::new();
+        first.write_to(&mut bytes).unwrap();
+        second.write_to(&mut bytes).unwrap();
+
+        // Each read stops at the end of its bitmap
+        let mut reader = bytes.as_slice();
+        assert_eq!(Bitmap::read_from(&mut reader).unwrap(), first);
+        assert_eq!(Bitmap::read_from(&mut reader).unwrap(), second);
+        assert!(Bitmap::read_from(&mut reader).is_err());
+        assert!(Bitmap::read_from(&bytes[..8]).is_err());
+    }
+
+    #[test]
+    fn test_bitmap_into_sparse() {
+        let bitmap = Bitmap::from("0110111001");
+        let sparse = SparseBitmap::from(&bitmap);
+
+        assert_eq!(
+            *sparse.runs,
+            [Run::new(0, 1), Run::new(3, 3), Run::new(7, 2)]
+        );
+        assert_eq!(sparse, SparseBitmap::from("0110111001"));
+    }
+
+    #[test]
+    fn test_bitmap_into_sparse_merges_chunks() {
+        let mut bitmap = Bitmap::new(200);
+        for position in 60..140 {
+            bitmap.set(position, true);
+        }
+
+        assert_eq!(*SparseBitmap::from(&bitmap).runs, [Run::new(60, 80)]);
+    }
+
+    #[test]
+    fn test_bitmap_into_sparse_ignores_padding() {
+        let bitmap = !&Bitmap::from("00000");
+
+        assert_eq!(SparseBitmap::from(&bitmap), SparseBitmap::from("11111"));
+    }
+
+    #[test]
+    fn test_sparse_into_bitmap() {
+        let sparse = SparseBitmap::from("0110111001");
+
+        assert_eq!(Bitmap::from(&sparse), Bitmap::from("0110111001"));
+
+        let mut sparse = SparseBitmap::new(200);
+        sparse.runs = vec![Run::new(3, 1), Run::new(62, 130)].into();
+
+        let bitmap = Bitmap::from(&sparse);
+        for position in 0..200 {
+            assert_eq!(bitmap.get(position), sparse.get(position));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_bitmap_eq_hash_ignore_padding() {
+        use std::collections::HashSet;
+
+        // The NOT operator leaves 1s beyond the size in the last chunk
+        let bitmap = !&Bitmap::from("000");
+        let ones = Bitmap::from("111");
+
+        assert_eq!(bitmap, ones);
+        assert_eq!(HashSet::from([bitmap, ones]).len(), 1);
+
+        let mut sparse = SparseBitmap::new(10);
+        sparse.runs = vec![Run::new(1, 2), Run::new(3, 1), Run::new(8, 5)].into();
+        let merged = SparseBitmap::from("1100001110");
+
+        assert_eq!(sparse, merged);
+        assert_eq!(HashSet::from([sparse, merged]).len(), 1);
+    }
+
+    #[test]
+    fn test_bitmap_ord() {
+        // Positions are compared from the lowest one, which is the rightmost character
+        assert!(Bitmap::from("01") > Bitmap::from("10"));
+        assert!(Bitmap::from("10") < Bitmap::from("010"));
+        assert!(Bitmap::from("1") > Bitmap::from("00"));
+        assert!(SparseBitmap::from("0110") > SparseBitmap::from("1100"));
+        assert!(SparseBitmap::from("0110") < SparseBitmap::from("1110"));
+        assert!(SparseBitmap::from("110") < SparseBitmap::from("0110"));
+    }
+
+    #[test]
+    fn test_bitmap_default_extend_index() {
+        let mut bitmap = Bitmap::default();
+        bitmap.extend([3, 1, 70]);
+
+        assert_eq!(bitmap.size, 71);
+        assert!(bitmap[1] && bitmap[3] && bitmap[70] && !bitmap[2]);
+        assert_eq!(bitmap.count_ones(), 3);
+
+        let mut sparse = SparseBitmap::default();
+        sparse.extend([1, 2, 3]);
+
+        assert_eq!(sparse, SparseBitmap::from("1110"));
+        assert!(sparse[1] && !sparse[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_bitmap_index_out_of_bounds() {
+        let _ = Bitmap::new(3)[3];
+    }
+
+    #[test]
+    fn test_bitmap_sum_product() {
+        let bitmaps = [
+            Bitmap::from("0011"),
+            Bitmap::from("0110"),
+            Bitmap::from("10010"),
+        ];
+
+        assert_eq!(bitmaps.iter().sum::<Bitmap>(), Bitmap::from("0111"));
+        assert_eq!(bitmaps.iter().product::<Bitmap>(), Bitmap::from("0010"));
+        assert_eq!(bitmaps.into_iter().sum::<Bitmap>(), Bitmap::from("0111"));
+        assert_eq!(
+            Vec::<Bitmap>::new().into_iter().sum::<Bitmap>(),
+            Bitmap::new(0)
+        );
+
+        let sparse = [SparseBitmap::from("1100"), SparseBitmap::from("0110")];
+        assert_eq!(
+            sparse.iter().sum::<SparseBitmap>(),
+            SparseBitmap::from("1110")
+        );
+        assert_eq!(
+            sparse.into_iter().product::<SparseBitmap>(),
+            SparseBitmap::from("0100")
+        );
+    }
+
+    fn bits() -> impl Strategy<Value = Vec<bool>> {
+        prop::collection::vec(prop::bool::weighted(0.3), 0..300)
+    }
+
+    proptest! {
+        #[test]
+        fn test_bitmap_sparse_round_trip(bits in bits()) {
+            let bitmap = bitmap(&bits);
+
+            let sparse = SparseBitmap::from(&bitmap);
+            prop_assert_eq!(sparse.to_string(), to_string(&bits));
+            prop_assert_eq!(Bitmap::from(&sparse), bitmap.clone());
+            prop_assert_eq!(Bitmap::from_bytes(&bitmap.to_bytes()), Some(bitmap));
+        }
+
+        #[test]
+        fn test_sparse_ranges_match_bits(bits in bits(), start in any::<usize>(), length in 0..40usize, value in any::<bool>()) {
+            let mut bits = bits;
+            let mut sparse = sparse(&bits);
+
+            let start = start % (bits.len() + 1);
+            let range = start..(start + length).min(bits.len());
+
+            prop_assert_eq!(sparse.contains_range(range.clone()), bits[range.clone()].iter().all(|bit| *bit));
+            prop_assert_eq!(sparse.intersects_range(range.clone()), bits[range.clone()].iter().any(|bit| *bit));
+
+            match value {
+                true => sparse.insert_range(range.clone()),
+                false => sparse.remove_range(range.clone()),
+            }
+            bits[range].fill(value);
+
+            prop_assert_eq!(sparse.validate(), Ok(()));
+            prop_assert_eq!(sparse.to_string(), to_string(&bits));
+        }
+
+        #[test]
+        fn test_sparse_rank_select_match_bits(bits in bits()) {
+            let sparse = sparse(&bits);
+            let ones = bits.iter().enumerate().filter(|(_, bit)| **bit).map(|(position, _)| position).collect::<Vec<_>>();
+
+            prop_assert_eq!(sparse.len(), ones.len());
+            prop_assert_eq!(sparse.first(), ones.first().copied());
+            prop_assert_eq!(sparse.last(), ones.last().copied());
+
+            for position in 0..=bits.len() {
+                let rank = bits[..position].iter().filter(|bit| **bit).count();
+                prop_assert_eq!(sparse.rank(position), rank);
+            }
+            for k in 0..=ones.len() {
+                prop_assert_eq!(sparse.select(k), ones.get(k).copied());
+            }
+        }
+
+        #[test]
+        fn test_runs_match_bits(bits in bits()) {
+            let bitmap = bitmap(&bits);
+
+            let mut runs: Vec<Range<usize>> = Vec::new();
+            for position in bits.iter().enumerate().filter(|(_, bit)| **bit).map(|(position, _)| position) {
+                match runs.last_mut() {
+                    Some(run) if run.end == position => run.end += 1,
+                    _ => runs.push(position..position + 1),
+                }
+            }
+
+            prop_assert_eq!(bitmap.runs().collect::<Vec<_>>(), runs.clone());
+            prop_assert_eq!(SparseBitmap::from(&bitmap).runs().collect::<Vec<_>>(), runs);
+        }
+
+        #[test]
+        fn test_sparse_from_ranges_match_bits(ranges in prop::collection::vec((0..200usize, 0..30usize), 0..20)) {
+            let ranges = ranges.into_iter().map(|(start, length)| start..start + length).collect::<Vec<_>>();
+            let mut bits = vec![false; 230];
+            for range in &ranges {
+                bits[range.clone()].fill(true);
+            }
+
+            let sparse = SparseBitmap::try_from_ranges(bits.len(), ranges.clone()).unwrap();
+            let mut sorted = ranges.clone();
+            sorted.sort_by_key(|range| range.start);
+
+            prop_assert_eq!(sparse.validate(), Ok(()));
+            prop_assert_eq!(sparse.to_string(), to_string(&bits));
+            prop_assert_eq!(SparseBitmap::from_sorted_ranges(bits.len(), sorted), Ok(sparse.clone()));
+            prop_assert_eq!(SparseBitmap::from(ranges).runs().collect::<Vec<_>>(), sparse.runs().collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn test_bitmap_ord_matches_bits(bits in bits(), other_bits in bits()) {
+            let (first, second) = (bitmap(&bits), bitmap(&other_bits));
+            let (first_sparse, second_sparse) = (SparseBitmap::from(&first), SparseBitmap::from(&second));
+
+            prop_assert_eq!(first.cmp(&second), bits.cmp(&other_bits));
+            prop_assert_eq!(first_sparse.cmp(&second_sparse), bits.cmp(&other_bits));
+            prop_assert_eq!(first_sparse == second_sparse, bits == other_bits);
+        }
+    }
 }
@@ -0,0 +1,151 @@
+//! Differential tests that run the same random operations over a `Bitmap`, a
+//! `SparseBitmap` and a `Vec<bool>` model, checking that all of them agree after
+//! every step. Proptest shrinks a failing sequence down to the fewest and shortest
+//! operations that still disagree.
+
+use alloc::{string::ToString, vec::Vec};
+use proptest::prelude::*;
+
+use crate::test_util::{self, to_string};
+use crate::{Bitmap, SparseBitmap};
+
+#[derive(Debug, Clone)]
+enum Operation {
+    /// Sets a value in the position at the given index, modulo the size
+    Set(usize, bool),
+    And(Vec<bool>),
+    Or(Vec<bool>),
+    Xor(Vec<bool>),
+    Not,
+}
+
+/// Bits made of runs of 0s and 1s, so that the sparse runs get merged and split
+fn bits() -> impl Strategy<Value = Vec<bool>> {
+    prop::collection::vec((any::<bool>(), 1..24usize), 0..12).prop_map(|runs| {
+        runs.into_iter()
+            .flat_map(|(value, length)| core::iter::repeat_n(value, length))
+            .collect()
+    })
+}
+
+fn operations(sets: bool) -> impl Strategy<Value = Vec<Operation>> {
+    let set =
+        (any::<usize>(), any::<bool>()).prop_map(|(index, value)| Operation::Set(index, value));
+    let operator = prop_oneof![
+        bits().prop_map(Operation::And),
+        bits().prop_map(Operation::Or),
+        bits().prop_map(Operation::Xor),
+        Just(Operation::Not),
+    ];
+
+    let operation = if sets {
+        prop_oneof![4 => set, 1 => operator].boxed()
+    } else {
+        operator.boxed()
+    };
+
+    prop::collection::vec(operation, 0..32)
+}
+
+fn combine(bits: &[bool], rhs: &[bool], operation: fn(bool, bool) -> bool) -> Vec<bool> {
+    bits.iter()
+        .zip(rhs)
+        .map(|(bit, rhs_bit)| operation(*bit, *rhs_bit))
+        .collect()
+}
+
+/// Builds the sparse bitmap with `set`, or from its string form, which has canonical runs
+fn sparse(bits: &[bool], sets: bool) -> SparseBitmap {
+    match sets {
+        true => test_util::sparse(bits),
+        false => SparseBitmap::from(to_string(bits).as_str()),
+    }
+}
+
+fn check(model: &[bool], bitmap: &Bitmap, sparse: &SparseBitmap) -> Result<(), TestCaseError> {
+    prop_assert_eq!(bitmap.size, model.len());
+    prop_assert_eq!(sparse.size, model.len());
+    prop_assert_eq!(sparse.validate(), Ok(()));
+
+    for (position, value) in model.iter().enumerate() {
+        prop_assert_eq!(bitmap.get(position), *value, "Bitmap position {}", position);
+        prop_assert_eq!(
+            sparse.get(position),
+            *value,
+            "SparseBitmap position {}",
+            position
+        );
+    }
+
+    let string = to_string(model);
+    prop_assert_eq!(bitmap, &Bitmap::from(string.as_str()));
+    prop_assert_eq!(&sparse.to_string(), &string);
+    prop_assert_eq!(&Bitmap::from(sparse), bitmap);
+    prop_assert_eq!(
+        bitmap.count_ones(),
+        model.iter().filter(|bit| **bit).count()
+    );
+
+    Ok(())
+}
+
+fn run(initial: Vec<bool>, operations: Vec<Operation>, sets: bool) -> Result<(), TestCaseError> {
+    let mut model = initial;
+    let mut bitmap = Bitmap::from(to_string(&model).as_str());
+    let mut sparse = sparse(&model, sets);
+
+    check(&model, &bitmap, &sparse)?;
+
+    for operation in operations {
+        match operation {
+            Operation::Set(index, value) => {
+                if model.is_empty() {
+                    continue;
+                }
+
+                let position = index % model.len();
+                model[position] = value;
+                bitmap.set(position, value);
+                sparse.set(position, value);
+            }
+            Operation::And(rhs) => {
+                bitmap = &bitmap & &Bitmap::from(to_string(&rhs).as_str());
+                sparse = &sparse & &self::sparse(&rhs, sets);
+                model = combine(&model, &rhs, |lhs, rhs| lhs & rhs);
+            }
+            Operation::Or(rhs) => {
+                bitmap = &bitmap | &Bitmap::from(to_string(&rhs).as_str());
+                sparse = &sparse | &self::sparse(&rhs, sets);
+                model = combine(&model, &rhs, |lhs, rhs| lhs | rhs);
+            }
+            Operation::Xor(rhs) => {
+                bitmap = &bitmap ^ &Bitmap::from(to_string(&rhs).as_str());
+                sparse = &sparse ^ &self::sparse(&rhs, sets);
+                model = combine(&model, &rhs, |lhs, rhs| lhs ^ rhs);
+            }
+            Operation::Not => {
+                bitmap = !&bitmap;
+                sparse = !&sparse;
+                model = model.iter().map(|bit| !bit).collect();
+            }
+        }
+
+        check(&model, &bitmap, &sparse)?;
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn test_oracle_operators(initial in bits(), operations in operations(false)) {
+        run(initial, operations, false)?;
+    }
+
+    #[test]
+    fn test_oracle_set_and_operators(initial in bits(), operations in operations(true)) {
+        run(initial, operations, true)?;
+    }
+}
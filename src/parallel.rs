@@ -0,0 +1,160 @@
+use rayon::prelude::*;
+
+use crate::{Bitmap, SparseBitmap};
+
+/// Minimum amount of chunks handled by every parallel task. Rayon splits the chunks
+/// in balanced ranges, but ranges below this length cost more to schedule than to
+/// compute.
+const MIN_CHUNKS_PER_TASK: usize = 1 << 10;
+
+impl Bitmap {
+    /// Applies a bitwise AND with `rhs`, computing ranges of chunks in parallel
+    pub fn par_and(&self, rhs: &Bitmap) -> Bitmap {
+        self.par_combine(rhs, |lhs, rhs| lhs & rhs)
+    }
+
+    /// Applies a bitwise OR with `rhs`, computing ranges of chunks in parallel
+    pub fn par_or(&self, rhs: &Bitmap) -> Bitmap {
+        self.par_combine(rhs, |lhs, rhs| lhs | rhs)
+    }
+
+    /// Applies a bitwise XOR with `rhs`, computing ranges of chunks in parallel
+    pub fn par_xor(&self, rhs: &Bitmap) -> Bitmap {
+        self.par_combine(rhs, |lhs, rhs| lhs ^ rhs)
+    }
+
+    /// Count the amount of 1s in the bitmap, counting ranges of chunks in parallel
+    pub fn par_count_ones(&self) -> usize {
+        self.chunks
+            .par_iter()
+            .enumerate()
+            .with_min_len(MIN_CHUNKS_PER_TASK)
+            .map(|(index, chunk)| self.masked_chunk(index, *chunk).count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterate in parallel over the positions of the 1s in the bitmap. Collecting the
+    /// iterator keeps the positions in ascending order.
+    pub fn par_iter_ones(&self) -> impl ParallelIterator<Item = usize> + '_ {
+        self.chunks
+            .par_iter()
+            .enumerate()
+            .with_min_len(MIN_CHUNKS_PER_TASK)
+            .flat_map_iter(move |(index, chunk)| self.chunk_ones(index, *chunk))
+    }
+
+    /// Combines the chunks of both bitmaps with `operation`. As the sequential
+    /// operators, the result has the size of the smallest bitmap.
+    fn par_combine<F>(&self, rhs: &Bitmap, operation: F) -> Bitmap
+    where
+        F: Fn(usize, usize) -> usize + Sync,
+    {
+        let size = self.size.min(rhs.size);
+        let mut chunks = Bitmap::chunks_with_size(size);
+
+        chunks
+            .par_iter_mut()
+            .enumerate()
+            .with_min_len(MIN_CHUNKS_PER_TASK)
+            .for_each(|(id, chunk)| *chunk = operation(self.chunks[id], rhs.chunks[id]));
+
+        let mut bitmap = Bitmap { chunks, size };
+        bitmap.clear_padding();
+        bitmap
+    }
+}
+
+impl SparseBitmap {
+    /// Count the amount of 1s in the bitmap, counting ranges of runs in parallel
+    pub fn par_count_ones(&self) -> usize {
+        self.runs
+            .par_iter()
+            .map(|run| run.end().min(self.size).saturating_sub(run.start))
+            .sum()
+    }
+
+    /// Iterate in parallel over the positions of the 1s in the bitmap.
+    ///
+    /// The runs are split in ranges of runs, and long runs are split again in ranges
+    /// of positions, so that a single run covering most of the bitmap is still shared
+    /// between threads. Collecting the iterator keeps the positions in ascending order.
+    pub fn par_iter_ones(&self) -> impl ParallelIterator<Item = usize> + '_ {
+        self.runs
+            .par_iter()
+            .flat_map(move |run| (run.start..run.end().min(self.size)).into_par_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::bitmap;
+    use crate::Run;
+    use alloc::{vec, vec::Vec};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_par_operators_across_tasks() {
+        let size = 5 * MIN_CHUNKS_PER_TASK * usize::BITS as usize + 17;
+        let mut first = Bitmap::new(size);
+        let mut second = Bitmap::new(size);
+        for position in (0..size).step_by(3) {
+            first.set(position, true);
+        }
+        for position in (0..size).step_by(5) {
+            second.set(position, true);
+        }
+
+        assert_eq!(first.par_and(&second), &first & &second);
+        assert_eq!(first.par_or(&second), &first | &second);
+        assert_eq!(first.par_xor(&second), &first ^ &second);
+        assert_eq!(first.par_count_ones(), first.count_ones());
+        assert_eq!(
+            first.par_iter_ones().collect::<Vec<_>>(),
+            first.iter_ones().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_par_operators_clear_padding() {
+        // The bits of the larger bitmap beyond the smaller size share its only chunk
+        let (first, second) = (Bitmap::from("101"), !&Bitmap::new(100));
+
+        assert_eq!(first.par_or(&second).chunks, vec![0b111]);
+        assert_eq!(first.par_xor(&second).chunks, vec![0b010]);
+        assert_eq!(second.par_and(&first).chunks, vec![0b101]);
+    }
+
+    #[test]
+    fn test_par_sparse_splits_long_runs() {
+        let mut sparse = SparseBitmap::new(1_000_000);
+        sparse.runs = vec![Run::new(3, 900_000), Run::new(900_010, 5)].into();
+
+        assert_eq!(sparse.par_count_ones(), 900_005);
+        assert_eq!(
+            sparse.par_iter_ones().collect::<Vec<_>>(),
+            Bitmap::from(&sparse).iter_ones().collect::<Vec<_>>()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_par_matches_sequential(
+            bits in prop::collection::vec(any::<bool>(), 0..2000),
+            rhs_bits in prop::collection::vec(any::<bool>(), 0..2000),
+        ) {
+            let (first, second) = (bitmap(&bits), bitmap(&rhs_bits));
+            let sparse = SparseBitmap::from(&first);
+            let ones: Vec<usize> = first.iter_ones().collect();
+
+            prop_assert_eq!(first.par_and(&second), &first & &second);
+            prop_assert_eq!(first.par_or(&second), &first | &second);
+            prop_assert_eq!(first.par_xor(&second), &first ^ &second);
+            prop_assert_eq!(first.par_or(&second).chunks, (&first | &second).chunks);
+            prop_assert_eq!(first.par_count_ones(), ones.len());
+            prop_assert_eq!(first.par_iter_ones().collect::<Vec<_>>(), ones.clone());
+            prop_assert_eq!(sparse.par_count_ones(), ones.len());
+            prop_assert_eq!(sparse.par_iter_ones().collect::<Vec<_>>(), ones);
+        }
+    }
+}
@@ -0,0 +1,790 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::mem::size_of;
+use core::ops::{BitAnd, BitOr, BitXor, Not};
+
+use crate::run_store::{Store, VecRuns};
+use crate::{chunks_count, Bitmap, Run, SparseBitmap};
+
+/// Amount of positions covered by every container of a `RoaringBitmap`
+const BLOCK_SIZE: usize = 1 << 16;
+
+/// Maximum amount of 1s stored in an array container. Beyond it, a dense container
+/// takes less memory than the array of positions.
+const ARRAY_MAX_SIZE: usize = 4096;
+
+/// RoaringBitmap partitions the positions in blocks of 2^16 bits, and stores every
+/// block in the container that fits its distribution best: an array of positions for
+/// blocks with a few 1s, a `Bitmap` for blocks with many scattered 1s, and a
+/// `SparseBitmap` for blocks made of long runs of 1s. Blocks without any 1 are
+/// not stored at all.
+#[derive(Debug, Clone)]
+pub struct RoaringBitmap {
+    blocks: Vec<Block>,
+    pub size: usize,
+}
+
+impl RoaringBitmap {
+    /// Creates a new `RoaringBitmap` with a fixed size
+    pub fn new(size: usize) -> RoaringBitmap {
+        RoaringBitmap {
+            blocks: Vec::new(),
+            size,
+        }
+    }
+
+    /// Get the bit value from a given position
+    #[inline(always)]
+    pub fn get(&self, position: usize) -> bool {
+        if position >= self.size {
+            return false;
+        }
+
+        let (key, index) = block_index(position);
+
+        self.blocks
+            .binary_search_by_key(&key, |block| block.key)
+            .is_ok_and(|found| self.blocks[found].container.get(index))
+    }
+
+    /// Set a bit value in a given position
+    #[inline(always)]
+    pub fn set(&mut self, position: usize, value: bool) {
+        if position >= self.size {
+            panic!("Index out of bounds");
+        }
+
+        let (key, index) = block_index(position);
+
+        match self.blocks.binary_search_by_key(&key, |block| block.key) {
+            Ok(found) => {
+                let container = &mut self.blocks[found].container;
+                container.set(index, value);
+
+                // Blocks without any 1 are not stored
+                if !value && container.is_empty() {
+                    self.blocks.remove(found);
+                }
+            }
+            Err(insert_at) if value => self.blocks.insert(
+                insert_at,
+                Block {
+                    key,
+                    container: Container::Array(vec![index as u16]),
+                },
+            ),
+            Err(_) => {}
+        }
+    }
+
+    /// Count the amount of 1s in the bitmap
+    pub fn count_ones(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|block| block.container.count_ones())
+            .sum()
+    }
+
+    /// Iterate over the positions of the 1s in the bitmap, in ascending order
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks.iter().flat_map(|block| {
+            let offset = block.key * BLOCK_SIZE;
+            block.container.iter().map(move |index| offset + index)
+        })
+    }
+
+    /// Converts every container into the kind that takes the least memory for its
+    /// content. Run containers are only created by this operation, since modifying
+    /// them turns them back into array or dense containers.
+    pub fn optimize(&mut self) {
+        for block in &mut self.blocks {
+            block.container = block.container.optimize();
+        }
+    }
+
+    /// Amount of positions of the block with the given key that are inside the bitmap.
+    #[inline(always)]
+    fn block_length(&self, key: usize) -> usize {
+        (self.size - key * BLOCK_SIZE).min(BLOCK_SIZE)
+    }
+
+    /// Combines the blocks of both bitmaps with the same key using `operation`, and
+    /// keeps the non-empty results that are inside the smallest bitmap size.
+    fn combine<F>(&self, rhs: &RoaringBitmap, operation: F) -> RoaringBitmap
+    where
+        F: Fn(Option<&Container>, Option<&Container>) -> Option<Container>,
+    {
+        let mut roaring = RoaringBitmap::new(self.size.min(rhs.size));
+        let blocks_count = chunks_count(roaring.size, BLOCK_SIZE);
+
+        let mut iter = self.blocks.iter().peekable();
+        let mut rhs_iter = rhs.blocks.iter().peekable();
+
+        loop {
+            // Take the block with the smallest key from each bitmap, or both if
+            // they share the key.
+            let (key, container) = match (iter.peek(), rhs_iter.peek()) {
+                (Some(block), Some(rhs_block)) if block.key == rhs_block.key => (
+                    block.key,
+                    operation(
+                        iter.next().map(|b| &b.container),
+                        rhs_iter.next().map(|b| &b.container),
+                    ),
+                ),
+                (Some(block), Some(rhs_block)) if block.key < rhs_block.key => (
+                    block.key,
+                    operation(iter.next().map(|b| &b.container), None),
+                ),
+                (_, Some(rhs_block)) => (
+                    rhs_block.key,
+                    operation(None, rhs_iter.next().map(|b| &b.container)),
+                ),
+                (Some(block), None) => (
+                    block.key,
+                    operation(iter.next().map(|b| &b.container), None),
+                ),
+                (None, None) => break,
+            };
+
+            if key >= blocks_count {
+                break;
+            }
+
+            let Some(mut container) = container else {
+                continue;
+            };
+
+            // Bitmaps of different sizes may leave 1s beyond the result size
+            let length = roaring.block_length(key);
+            if self.size != rhs.size && length < BLOCK_SIZE {
+                container = container.truncate(length);
+            }
+
+            if container.count_ones() > 0 {
+                roaring.blocks.push(Block { key, container });
+            }
+        }
+
+        roaring
+    }
+}
+
+impl PartialEq for RoaringBitmap {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.iter_ones().eq(other.iter_ones())
+    }
+}
+
+impl Eq for RoaringBitmap {}
+
+impl BitAnd for &RoaringBitmap {
+    type Output = RoaringBitmap;
+
+    #[inline(always)]
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |container, rhs_container| {
+            Some(container?.and(rhs_container?))
+        })
+    }
+}
+
+impl BitOr for &RoaringBitmap {
+    type Output = RoaringBitmap;
+
+    #[inline(always)]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |container, rhs_container| {
+            match (container, rhs_container) {
+                (Some(container), Some(rhs_container)) => Some(container.or(rhs_container)),
+                (container, rhs_container) => container.or(rhs_container).cloned(),
+            }
+        })
+    }
+}
+
+impl BitXor for &RoaringBitmap {
+    type Output = RoaringBitmap;
+
+    #[inline(always)]
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        self.combine(rhs, |container, rhs_container| {
+            match (container, rhs_container) {
+                (Some(container), Some(rhs_container)) => Some(container.xor(rhs_container)),
+                (container, rhs_container) => container.or(rhs_container).cloned(),
+            }
+        })
+    }
+}
+
+impl Not for &RoaringBitmap {
+    type Output = RoaringBitmap;
+
+    #[inline(always)]
+    fn not(self) -> Self::Output {
+        let mut roaring = RoaringBitmap::new(self.size);
+        let mut blocks = self.blocks.iter().peekable();
+
+        for key in 0..chunks_count(self.size, BLOCK_SIZE) {
+            let length = self.block_length(key);
+
+            // Missing blocks have only 0s, so their negation is a single run
+            let container = match blocks.next_if(|block| block.key == key) {
+                Some(block) => block.container.not(length),
+                None => Container::from_runs(vec![Run::new(0, length)]),
+            };
+
+            if container.count_ones() > 0 {
+                roaring.blocks.push(Block { key, container });
+            }
+        }
+
+        roaring
+    }
+}
+
+impl From<&str> for RoaringBitmap {
+    fn from(value: &str) -> Self {
+        let mut roaring = RoaringBitmap::new(value.len());
+
+        for (index, char) in value.chars().rev().enumerate() {
+            match char {
+                '1' => roaring.set(index, true),
+                '0' => roaring.set(index, false),
+                _ => panic!("Can not convert from string slice. Unexpected character {char}"),
+            }
+        }
+
+        roaring
+    }
+}
+
+impl From<&Bitmap> for RoaringBitmap {
+    fn from(bitmap: &Bitmap) -> Self {
+        let mut roaring = RoaringBitmap::new(bitmap.size);
+        let block_chunks = chunks_count(BLOCK_SIZE, usize::BITS as usize);
+
+        for (key, chunks) in bitmap.chunks.chunks(block_chunks).enumerate() {
+            // Only the last chunk may have padding bits beyond the bitmap size
+            let mut block = Bitmap::new(BLOCK_SIZE);
+            for (index, chunk) in chunks.iter().enumerate() {
+                block.chunks[index] = bitmap.masked_chunk(key * block_chunks + index, *chunk);
+            }
+
+            let container = Container::from_bitmap(block);
+            if container.count_ones() > 0 {
+                roaring.blocks.push(Block { key, container });
+            }
+        }
+
+        roaring
+    }
+}
+
+impl From<&RoaringBitmap> for Bitmap {
+    fn from(roaring: &RoaringBitmap) -> Self {
+        let mut bitmap = Bitmap::new(roaring.size);
+
+        for block in &roaring.blocks {
+            let offset = block.key * BLOCK_SIZE;
+
+            for run in block.container.runs() {
+                bitmap.fill(offset + run.start, offset + run.end());
+            }
+        }
+
+        bitmap
+    }
+}
+
+/// Block stores the container with the positions `key * BLOCK_SIZE..(key + 1) * BLOCK_SIZE`
+#[derive(Debug, Clone)]
+struct Block {
+    key: usize,
+    container: Container,
+}
+
+/// Container stores the 1s of a single block, indexed from the start of the block.
+#[derive(Debug, Clone)]
+enum Container {
+    /// Sorted positions of the 1s in the block
+    Array(Vec<u16>),
+    /// `Bitmap` of `BLOCK_SIZE` bits
+    Dense(Bitmap),
+    /// `SparseBitmap` of `BLOCK_SIZE` bits
+    Runs(SparseBitmap),
+}
+
+impl Container {
+    /// Creates an array or a dense container from a `Bitmap` of `BLOCK_SIZE` bits,
+    /// depending on the amount of 1s.
+    fn from_bitmap(bitmap: Bitmap) -> Container {
+        if bitmap.count_ones() <= ARRAY_MAX_SIZE {
+            Container::Array(bitmap.iter_ones().map(|index| index as u16).collect())
+        } else {
+            Container::Dense(bitmap)
+        }
+    }
+
+    /// Creates an array container from sorted positions, or a dense container if
+    /// there are too many of them.
+    fn from_array(values: Vec<u16>) -> Container {
+        if values.len() <= ARRAY_MAX_SIZE {
+            Container::Array(values)
+        } else {
+            Container::Dense(Container::Array(values).to_bitmap())
+        }
+    }
+
+    /// Creates a run container from sorted and non-adjacent runs.
+    fn from_runs(runs: Vec<Run>) -> Container {
+        Container::Runs(SparseBitmap {
+            runs: VecRuns::from(runs),
+            size: BLOCK_SIZE,
+        })
+    }
+
+    #[inline(always)]
+    fn get(&self, index: usize) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&(index as u16)).is_ok(),
+            Container::Dense(bitmap) => bitmap.get(index),
+            Container::Runs(sparse) => sparse.get(index),
+        }
+    }
+
+    #[inline(always)]
+    fn set(&mut self, index: usize, value: bool) {
+        match self {
+            Container::Array(values) => {
+                match (values.binary_search(&(index as u16)), value) {
+                    (Err(insert_at), true) => values.insert(insert_at, index as u16),
+                    (Ok(found), false) => {
+                        values.remove(found);
+                    }
+                    _ => {}
+                }
+
+                if values.len() > ARRAY_MAX_SIZE {
+                    *self = Container::Dense(self.to_bitmap());
+                }
+            }
+            Container::Dense(bitmap) => bitmap.set(index, value),
+            Container::Runs(_) => {
+                let mut container = Container::from_bitmap(self.to_bitmap());
+                container.set(index, value);
+                *self = container;
+            }
+        }
+    }
+
+    fn count_ones(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len(),
+            Container::Dense(bitmap) => bitmap.count_ones(),
+            Container::Runs(sparse) => sparse.runs.iter().map(|run| run.length).sum(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Container::Array(values) => values.is_empty(),
+            Container::Dense(bitmap) => bitmap.chunks.iter().all(|chunk| *chunk == 0),
+            Container::Runs(sparse) => sparse.runs.is_empty(),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self {
+            Container::Array(values) => Box::new(values.iter().map(|value| *value as usize)),
+            Container::Dense(bitmap) => Box::new(bitmap.iter_ones()),
+            Container::Runs(sparse) => {
+                Box::new(sparse.runs.iter().flat_map(|run| run.start..run.end()))
+            }
+        }
+    }
+
+    /// Returns the content of the container as a `Bitmap` of `BLOCK_SIZE` bits.
+    fn to_bitmap(&self) -> Bitmap {
+        match self {
+            Container::Array(values) => {
+                let mut bitmap = Bitmap::new(BLOCK_SIZE);
+                for value in values {
+                    bitmap.set(*value as usize, true);
+                }
+                bitmap
+            }
+            Container::Dense(bitmap) => bitmap.clone(),
+            Container::Runs(sparse) => Bitmap::from(sparse),
+        }
+    }
+
+    /// Returns the content of the container as sorted and non-adjacent runs.
+    fn runs(&self) -> Vec<Run> {
+        match self {
+            Container::Array(values) => {
+                let mut sparse = SparseBitmap::new(BLOCK_SIZE);
+                for value in values {
+                    sparse.runs.push(Run::new(*value as usize, 1));
+                }
+                sparse.runs.into_vec()
+            }
+            Container::Dense(bitmap) => SparseBitmap::from(bitmap).runs.into_vec(),
+            Container::Runs(sparse) => sparse.runs.to_vec(),
+        }
+    }
+
+    /// Returns the container kind that takes the least memory for the same content.
+    fn optimize(&self) -> Container {
+        let cardinality = self.count_ones();
+        let runs = self.runs();
+
+        let runs_bytes = runs.len() * size_of::<Run>();
+        let array_bytes = cardinality * size_of::<u16>();
+        let dense_bytes = BLOCK_SIZE / 8;
+
+        if runs_bytes < array_bytes.min(dense_bytes) {
+            Container::from_runs(runs)
+        } else if cardinality <= ARRAY_MAX_SIZE {
+            Container::Array(self.iter().map(|index| index as u16).collect())
+        } else {
+            Container::Dense(self.to_bitmap())
+        }
+    }
+
+    /// Returns the container without the 1s from `length` onwards.
+    fn truncate(&self, length: usize) -> Container {
+        let limit = Run::new(0, length);
+        let runs = self
+            .runs()
+            .iter()
+            .filter_map(|run| run.intersect(&limit))
+            .filter(|run| run.length > 0)
+            .collect();
+
+        Container::from_runs(runs).optimize()
+    }
+
+    fn and(&self, rhs: &Container) -> Container {
+        match (self, rhs) {
+            (Container::Array(values), Container::Array(rhs_values)) => {
+                Container::Array(merge(values, rhs_values, |left, right| left && right))
+            }
+            (Container::Array(values), other) | (other, Container::Array(values)) => {
+                Container::Array(
+                    values
+                        .iter()
+                        .copied()
+                        .filter(|value| other.get(*value as usize))
+                        .collect(),
+                )
+            }
+            _ => Container::from_bitmap(&self.to_bitmap() & &rhs.to_bitmap()),
+        }
+    }
+
+    fn or(&self, rhs: &Container) -> Container {
+        match (self, rhs) {
+            (Container::Array(values), Container::Array(rhs_values)) => {
+                Container::from_array(merge(values, rhs_values, |left, right| left || right))
+            }
+            (Container::Array(values), Container::Dense(bitmap))
+            | (Container::Dense(bitmap), Container::Array(values)) => {
+                let mut bitmap = bitmap.clone();
+                for value in values {
+                    bitmap.set(*value as usize, true);
+                }
+                Container::from_bitmap(bitmap)
+            }
+            _ => Container::from_bitmap(&self.to_bitmap() | &rhs.to_bitmap()),
+        }
+    }
+
+    fn xor(&self, rhs: &Container) -> Container {
+        match (self, rhs) {
+            (Container::Array(values), Container::Array(rhs_values)) => {
+                Container::from_array(merge(values, rhs_values, |left, right| left != right))
+            }
+            (Container::Array(values), Container::Dense(bitmap))
+            | (Container::Dense(bitmap), Container::Array(values)) => {
+                let mut bitmap = bitmap.clone();
+                for value in values {
+                    let index = *value as usize;
+                    bitmap.set(index, !bitmap.get(index));
+                }
+                Container::from_bitmap(bitmap)
+            }
+            _ => Container::from_bitmap(&self.to_bitmap() ^ &rhs.to_bitmap()),
+        }
+    }
+
+    /// Negates the first `length` positions of the container, by collecting the gaps
+    /// in-between its runs.
+    fn not(&self, length: usize) -> Container {
+        let mut start = 0;
+        let mut runs = Vec::new();
+
+        for run in self.runs() {
+            if start < run.start {
+                runs.push(Run::new(start, run.start - start));
+            }
+            start = run.end();
+        }
+
+        if start < length {
+            runs.push(Run::new(start, length - start));
+        }
+
+        Container::from_runs(runs).optimize()
+    }
+}
+
+/// Merges two sorted arrays, keeping the values for which `keep` returns true given
+/// whether the value is present in the left and in the right array.
+fn merge<F>(values: &[u16], rhs_values: &[u16], keep: F) -> Vec<u16>
+where
+    F: Fn(bool, bool) -> bool,
+{
+    let mut result = Vec::with_capacity(values.len().max(rhs_values.len()));
+
+    let mut iter = values.iter().peekable();
+    let mut rhs_iter = rhs_values.iter().peekable();
+
+    loop {
+        let value = match (iter.peek(), rhs_iter.peek()) {
+            (Some(value), Some(rhs_value)) => **value.min(rhs_value),
+            (Some(value), None) => **value,
+            (None, Some(rhs_value)) => **rhs_value,
+            (None, None) => break,
+        };
+
+        let left = iter.next_if_eq(&&value).is_some();
+        let right = rhs_iter.next_if_eq(&&value).is_some();
+
+        if keep(left, right) {
+            result.push(value);
+        }
+    }
+
+    result
+}
+
+/// Calculate the block key and the index inside the block for a given position.
+#[inline(always)]
+fn block_index(position: usize) -> (usize, usize) {
+    (position / BLOCK_SIZE, position % BLOCK_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Creates a bitmap with a block of each container kind: an array in the first
+    /// block, a dense bitmap in the second one, and a run in the third one.
+    fn mixed(size: usize, seed: usize) -> RoaringBitmap {
+        let mut roaring = RoaringBitmap::new(size);
+
+        for position in (seed..BLOCK_SIZE).step_by(97) {
+            roaring.set(position, true);
+        }
+        for position in (BLOCK_SIZE + seed..2 * BLOCK_SIZE).step_by(3) {
+            roaring.set(position, true);
+        }
+        for position in 2 * BLOCK_SIZE + seed * 100..2 * BLOCK_SIZE + 30000 {
+            roaring.set(position, true);
+        }
+
+        roaring.optimize();
+        roaring
+    }
+
+    fn positions(bitmap: &Bitmap) -> Vec<usize> {
+        bitmap.iter_ones().collect()
+    }
+
+    #[test]
+    fn test_roaring_get_set() {
+        let mut roaring = RoaringBitmap::new(200_000);
+
+        roaring.set(3, true);
+        roaring.set(70_000, true);
+        roaring.set(199_999, true);
+
+        assert!(roaring.get(3));
+        assert!(roaring.get(70_000));
+        assert!(roaring.get(199_999));
+        assert!(!roaring.get(4));
+        assert!(!roaring.get(200_000));
+        assert_eq!(roaring.blocks.len(), 3);
+
+        roaring.set(70_000, false);
+
+        assert!(!roaring.get(70_000));
+        assert_eq!(roaring.count_ones(), 2);
+        assert_eq!(roaring.blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_roaring_clearing_last_one_removes_block() {
+        let mut roaring = RoaringBitmap::new(BLOCK_SIZE);
+
+        roaring.set(3, true);
+        roaring.set(3, false);
+        assert!(roaring.blocks.is_empty());
+
+        for position in 0..=ARRAY_MAX_SIZE {
+            roaring.set(position, true);
+        }
+        for position in 0..=ARRAY_MAX_SIZE {
+            roaring.set(position, false);
+        }
+        assert!(roaring.blocks.is_empty());
+        assert_eq!(roaring, RoaringBitmap::new(BLOCK_SIZE));
+    }
+
+    #[test]
+    fn test_roaring_from_bitmap_with_partial_last_block() {
+        let size = 3 * BLOCK_SIZE + 100;
+        let mut bitmap = Bitmap::new(size);
+        bitmap.set(9, true);
+        bitmap.set(3 * BLOCK_SIZE + 99, true);
+
+        let roaring = RoaringBitmap::from(&bitmap);
+
+        assert_eq!(
+            roaring.iter_ones().collect::<Vec<_>>(),
+            vec![9, 3 * BLOCK_SIZE + 99]
+        );
+        assert_eq!(Bitmap::from(&roaring), bitmap);
+    }
+
+    #[test]
+    fn test_roaring_from_str() {
+        let roaring = RoaringBitmap::from("11001");
+
+        assert_eq!(roaring.iter_ones().collect::<Vec<_>>(), vec![0, 3, 4]);
+        assert_eq!(Bitmap::from(&roaring), Bitmap::from("11001"));
+    }
+
+    #[test]
+    fn test_roaring_array_becomes_dense() {
+        let mut roaring = RoaringBitmap::new(BLOCK_SIZE);
+
+        for position in 0..=ARRAY_MAX_SIZE {
+            roaring.set(position * 2, true);
+        }
+
+        assert!(matches!(roaring.blocks[0].container, Container::Dense(_)));
+        assert_eq!(roaring.count_ones(), ARRAY_MAX_SIZE + 1);
+    }
+
+    #[test]
+    fn test_roaring_optimize() {
+        let roaring = mixed(3 * BLOCK_SIZE, 1);
+
+        assert!(matches!(roaring.blocks[0].container, Container::Array(_)));
+        assert!(matches!(roaring.blocks[1].container, Container::Dense(_)));
+        assert!(matches!(roaring.blocks[2].container, Container::Runs(_)));
+    }
+
+    #[test]
+    fn test_roaring_set_in_runs() {
+        let mut roaring = mixed(3 * BLOCK_SIZE, 1);
+
+        roaring.set(2 * BLOCK_SIZE + 200, false);
+
+        assert!(!roaring.get(2 * BLOCK_SIZE + 200));
+        assert!(roaring.get(2 * BLOCK_SIZE + 201));
+        assert_eq!(
+            roaring.count_ones(),
+            mixed(3 * BLOCK_SIZE, 1).count_ones() - 1
+        );
+    }
+
+    #[test]
+    fn test_roaring_operators_across_containers() {
+        let first = mixed(3 * BLOCK_SIZE, 1);
+        let second = mixed(3 * BLOCK_SIZE, 2);
+
+        let dense = Bitmap::from(&first);
+        let rhs_dense = Bitmap::from(&second);
+
+        let and = &first & &second;
+        let or = &first | &second;
+        let xor = &first ^ &second;
+
+        assert_eq!(
+            positions(&Bitmap::from(&and)),
+            positions(&(&dense & &rhs_dense))
+        );
+        assert_eq!(
+            positions(&Bitmap::from(&or)),
+            positions(&(&dense | &rhs_dense))
+        );
+        assert_eq!(
+            positions(&Bitmap::from(&xor)),
+            positions(&(&dense ^ &rhs_dense))
+        );
+        assert_eq!(positions(&Bitmap::from(&!&first)), positions(&!&dense));
+    }
+
+    #[test]
+    fn test_roaring_not() {
+        assert_eq!(!&RoaringBitmap::from("10101"), RoaringBitmap::from("01010"));
+        assert_eq!(!&RoaringBitmap::from("00000"), RoaringBitmap::from("11111"));
+        assert_eq!(!&RoaringBitmap::from("11111"), RoaringBitmap::from("00000"));
+    }
+
+    #[test]
+    fn test_roaring_operators_with_different_sizes() {
+        let first = RoaringBitmap::from("0011");
+        let second = RoaringBitmap::from("11110");
+
+        assert_eq!(&first | &second, RoaringBitmap::from("1111"));
+        assert_eq!(&first ^ &second, RoaringBitmap::from("1101"));
+        assert_eq!(&first & &second, RoaringBitmap::from("0010"));
+    }
+
+    fn roaring_and_bitmap(size: usize) -> impl Strategy<Value = (RoaringBitmap, Bitmap)> {
+        let ranges = prop::collection::vec((0..size, 0..3000usize), 0..20);
+        let points = prop::collection::vec(0..size, 0..5000);
+
+        (ranges, points, any::<bool>()).prop_map(move |(ranges, points, optimize)| {
+            let mut roaring = RoaringBitmap::new(size);
+            let mut bitmap = Bitmap::new(size);
+
+            let ranges = ranges
+                .into_iter()
+                .flat_map(|(start, length)| start..(start + length).min(size));
+
+            for position in ranges.chain(points) {
+                roaring.set(position, true);
+                bitmap.set(position, true);
+            }
+
+            if optimize {
+                roaring.optimize();
+            }
+
+            (roaring, bitmap)
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn test_roaring_matches_bitmap(
+            (first, dense) in roaring_and_bitmap(3 * BLOCK_SIZE + 100),
+            (second, rhs_dense) in roaring_and_bitmap(3 * BLOCK_SIZE + 100),
+        ) {
+            prop_assert_eq!(Bitmap::from(&first), dense.clone());
+            prop_assert_eq!(first.count_ones(), dense.count_ones());
+            prop_assert_eq!(RoaringBitmap::from(&dense), first.clone());
+
+            prop_assert_eq!(positions(&Bitmap::from(&(&first & &second))), positions(&(&dense & &rhs_dense)));
+            prop_assert_eq!(positions(&Bitmap::from(&(&first | &second))), positions(&(&dense | &rhs_dense)));
+            prop_assert_eq!(positions(&Bitmap::from(&(&first ^ &second))), positions(&(&dense ^ &rhs_dense)));
+            prop_assert_eq!(positions(&Bitmap::from(&!&first)), positions(&!&dense));
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, Range};
+
+/// RunStore is where a `SparseBitmap` keeps its runs of 1s, in their canonical form.
+///
+/// `VecRuns` takes the least memory and is the fastest to read, while `TreeRuns` sets
+/// positions and ranges without moving the runs after them. The trait is sealed, so
+/// these are the only stores.
+pub trait RunStore: sealed::Store {}
+
+impl RunStore for VecRuns {}
+
+pub(crate) use sealed::Store;
+
+mod sealed {
+    use core::fmt;
+
+    use super::Run;
+
+    /// Store holds the operations every run store implements, with the same semantics
+    /// as the `SparseBitmap` ones. All of them but `iter`, `count`, `push` and `retain`
+    /// expect the runs to be in their canonical form.
+    pub trait Store: Clone + Default + fmt::Debug {
+        /// Iterate over the runs in the order they are stored
+        fn iter(&self) -> impl Iterator<Item = Run> + '_;
+
+        /// Amount of runs
+        fn count(&self) -> usize;
+
+        /// Amount of 1s in all the runs
+        fn ones(&self) -> usize;
+
+        fn first(&self) -> Option<Run>;
+
+        fn last(&self) -> Option<Run>;
+
+        /// The last run starting at or before the position, the only one that can
+        /// contain it
+        fn floor(&self, position: usize) -> Option<Run>;
+
+        /// Sets a 1 to every position of the run, merging the runs it overlaps or touches
+        fn insert(&mut self, run: Run);
+
+        /// Sets a 0 to every position of the run, trimming or splitting the runs it overlaps
+        fn remove(&mut self, run: Run);
+
+        /// Adds a run after the last one, merging both when they overlap or touch. The
+        /// run must not start before the last one.
+        fn push(&mut self, run: Run);
+
+        /// Keeps only the runs for which `keep` returns true
+        fn retain<F: FnMut(&Run) -> bool>(&mut self, keep: F);
+
+        /// Removes every 1 at or beyond `size`
+        fn truncate(&mut self, size: usize);
+
+        /// Count the amount of 1s before a given position
+        fn rank(&self, position: usize) -> usize;
+
+        /// Position of the `k`-th 1, counting from 0
+        fn select(&self, k: usize) -> Option<usize>;
+    }
+}
+
+/// VecRuns keeps the runs in a `Vec` sorted by their start, along with the amount of
+/// 1s before each of them. Reading a position, `rank` and `select` are binary
+/// searches, but inserting or removing a run moves all the runs after it, and shifts
+/// their amount of 1s.
+#[derive(Debug, Clone, Default)]
+pub struct VecRuns {
+    runs: Vec<Run>,
+    /// Amount of 1s before each run
+    ones: Vec<usize>,
+}
+
+impl VecRuns {
+    pub(crate) fn into_vec(self) -> Vec<Run> {
+        self.runs
+    }
+
+    /// Replaces the runs in `range` with `runs`, shifting the amount of 1s before the
+    /// runs after them by the amount of 1s added and removed.
+    fn replace<I: Iterator<Item = Run> + Clone>(&mut self, range: Range<usize>, runs: I) {
+        let removed: usize = self.runs[range.clone()].iter().map(|run| run.length).sum();
+        let added: usize = runs.clone().map(|run| run.length).sum();
+        let end = range.start + runs.clone().count();
+        let mut ones = self.ones.get(range.start).copied().unwrap_or(self.ones());
+
+        self.runs.splice(range.clone(), runs.clone());
+        self.ones.splice(
+            range,
+            runs.map(|run| {
+                ones += run.length;
+                ones - run.length
+            }),
+        );
+
+        for ones in &mut self.ones[end..] {
+            *ones = *ones + added - removed;
+        }
+    }
+
+    /// Counts again the 1s before every run
+    fn recount(&mut self) {
+        let mut ones = 0;
+
+        self.ones.clear();
+        for run in &self.runs {
+            self.ones.push(ones);
+            ones += run.length;
+        }
+    }
+}
+
+impl From<Vec<Run>> for VecRuns {
+    fn from(runs: Vec<Run>) -> Self {
+        let mut runs = VecRuns {
+            runs,
+            ones: Vec::new(),
+        };
+        runs.recount();
+        runs
+    }
+}
+
+impl Deref for VecRuns {
+    type Target = [Run];
+
+    fn deref(&self) -> &Self::Target {
+        &self.runs
+    }
+}
+
+impl Store for VecRuns {
+    fn iter(&self) -> impl Iterator<Item = Run> + '_ {
+        self.runs.iter().copied()
+    }
+
+    fn count(&self) -> usize {
+        self.runs.len()
+    }
+
+    fn ones(&self) -> usize {
+        match (self.ones.last(), self.runs.last()) {
+            (Some(ones), Some(run)) => ones + run.length,
+            _ => 0,
+        }
+    }
+
+    fn first(&self) -> Option<Run> {
+        self.runs.first().copied()
+    }
+
+    fn last(&self) -> Option<Run> {
+        self.runs.last().copied()
+    }
+
+    #[inline(always)]
+    fn floor(&self, position: usize) -> Option<Run> {
+        let index = self.runs.partition_point(|run| run.start <= position);
+
+        index.checked_sub(1).map(|index| self.runs[index])
+    }
+
+    fn insert(&mut self, run: Run) {
+        // Runs from `first` to `last` overlap the run or end right before or start
+        // right after it, and all of them are replaced by their union
+        let first = self
+            .runs
+            .partition_point(|existing| existing.end() < run.start);
+        let last = self
+            .runs
+            .partition_point(|existing| existing.start <= run.end());
+
+        let union = self.runs[first..last].iter().fold(run, |union, existing| {
+            union.union(existing).unwrap_or(union)
+        });
+
+        self.replace(first..last, [union].into_iter());
+    }
+
+    fn remove(&mut self, run: Run) {
+        // Runs from `first` to `last` overlap the run
+        let first = self
+            .runs
+            .partition_point(|existing| existing.end() <= run.start);
+        let last = self
+            .runs
+            .partition_point(|existing| existing.start < run.end());
+
+        if first == last {
+            return;
+        }
+
+        // Only the first and the last overlapped runs can have 1s left around the run
+        let left = Run::from_range((self.runs[first].start.min(run.start), run.start));
+        let right = Run::from_range((run.end(), self.runs[last - 1].end().max(run.end())));
+
+        self.replace(
+            first..last,
+            [left, right].into_iter().filter(|run| run.length > 0),
+        );
+    }
+
+    #[inline(always)]
+    fn push(&mut self, run: Run) {
+        let ones = self.ones();
+
+        if let Some((last, union)) = self
+            .runs
+            .last_mut()
+            .and_then(|last| last.union(&run).map(|union| (last, union)))
+        {
+            *last = union;
+        } else {
+            self.runs.push(run);
+            self.ones.push(ones);
+        }
+    }
+
+    fn retain<F: FnMut(&Run) -> bool>(&mut self, keep: F) {
+        self.runs.retain(keep);
+        self.recount();
+    }
+
+    fn truncate(&mut self, size: usize) {
+        self.runs.retain_mut(|run| {
+            run.length = run.end().min(size).saturating_sub(run.start);
+            run.length > 0
+        });
+        self.recount();
+    }
+
+    fn rank(&self, position: usize) -> usize {
+        let index = self.runs.partition_point(|run| run.start < position);
+
+        // Every run before the last one starting before the position is entirely before it
+        match index.checked_sub(1) {
+            Some(last) => {
+                self.ones[last] + self.runs[last].end().min(position) - self.runs[last].start
+            }
+            None => 0,
+        }
+    }
+
+    fn select(&self, k: usize) -> Option<usize> {
+        // The last run with at most `k` 1s before it is the only one that can hold it
+        let index = self
+            .ones
+            .partition_point(|ones| *ones <= k)
+            .checked_sub(1)?;
+        let offset = k - self.ones[index];
+
+        (offset < self.runs[index].length).then(|| self.runs[index].start + offset)
+    }
+}
+
+// Run represents a range in a `SparseBitmap`, where 1s are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Run {
+    pub(crate) start: usize,
+    pub(crate) length: usize,
+}
+
+impl Run {
+    pub(crate) fn new(start: usize, length: usize) -> Run {
+        Run { start, length }
+    }
+
+    pub(crate) fn from_range((start, end): (usize, usize)) -> Run {
+        Run::new(start, end - start)
+    }
+
+    #[inline(always)]
+    pub(crate) fn end(&self) -> usize {
+        self.start + self.length
+    }
+
+    #[inline(always)]
+    pub(crate) fn intersect(&self, run: &Run) -> Option<Run> {
+        if self.matches(run) {
+            let start = self.start.max(run.start);
+            let end = self.end().min(run.end());
+
+            Some(Run::new(start, end - start))
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn union(&self, run: &Run) -> Option<Run> {
+        if self.matches(run) {
+            let start = self.start.min(run.start);
+            let end = self.end().max(run.end());
+
+            Some(Run::new(start, end - start))
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    fn matches(&self, run: &Run) -> bool {
+        self.contains(run.start) || run.contains(self.start)
+    }
+
+    #[inline(always)]
+    fn contains(&self, index: usize) -> bool {
+        index >= self.start && index <= self.end()
+    }
+}
@@ -0,0 +1,44 @@
+//! Helpers shared by the tests of every bitmap, to build them from a list of bits.
+
+use alloc::string::String;
+
+use crate::{BitMatrix, Bitmap, SparseBitmap};
+
+/// Builds a bitmap of any kind with `new`, setting every position of `bits` with `set`
+pub(crate) fn build<B>(bits: &[bool], new: fn(usize) -> B, set: fn(&mut B, usize, bool)) -> B {
+    let mut bitmap = new(bits.len());
+    for (position, value) in bits.iter().enumerate() {
+        set(&mut bitmap, position, *value);
+    }
+    bitmap
+}
+
+pub(crate) fn bitmap(bits: &[bool]) -> Bitmap {
+    build(bits, Bitmap::new, Bitmap::set)
+}
+
+/// Builds the sparse bitmap with `set`, so its runs are merged and split along the way
+pub(crate) fn sparse(bits: &[bool]) -> SparseBitmap {
+    build(bits, SparseBitmap::new, SparseBitmap::set)
+}
+
+/// The string form of the bits, with the highest position first as `From<&str>`
+pub(crate) fn to_string(bits: &[bool]) -> String {
+    bits.iter()
+        .rev()
+        .map(|bit| if *bit { '1' } else { '0' })
+        .collect()
+}
+
+/// Creates a matrix from rows of '0' and '1', with the first column at the left.
+pub(crate) fn matrix(rows: &[&str]) -> BitMatrix {
+    let mut matrix = BitMatrix::new(rows.len(), rows.first().map_or(0, |row| row.len()));
+
+    for (row, columns) in rows.iter().enumerate() {
+        for (column, char) in columns.chars().enumerate() {
+            matrix.set(row, column, char == '1');
+        }
+    }
+
+    matrix
+}
@@ -0,0 +1,546 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::run_store::{RunStore, Store};
+use crate::{Run, SparseBitmap};
+
+/// TreeSparseBitmap is a `SparseBitmap` keeping its runs in a `TreeRuns`, for
+/// workloads that insert and remove many runs.
+pub type TreeSparseBitmap = SparseBitmap<TreeRuns>;
+
+/// Index of a missing node
+const NIL: usize = usize::MAX;
+
+/// TreeRuns keeps the runs in a treap: a binary search tree ordered by the run starts,
+/// balanced by giving every node a random priority that is higher than the ones of
+/// its children.
+///
+/// Every node also counts the 1s of its subtree. Setting a position or a range,
+/// `rank` and `select` take `O(log n)`, without moving the runs after them as
+/// `VecRuns` does.
+#[derive(Clone)]
+pub struct TreeRuns {
+    /// Nodes of the tree, linked by their index
+    nodes: Vec<Node>,
+    /// Nodes removed from the tree, reused by the next ones
+    free: Vec<usize>,
+    root: usize,
+    /// State of the generator of the node priorities
+    seed: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    run: Run,
+    priority: u64,
+    left: usize,
+    right: usize,
+    /// Amount of runs in the subtree
+    count: usize,
+    /// Amount of 1s in the subtree
+    ones: usize,
+}
+
+impl RunStore for TreeRuns {}
+
+impl Default for TreeRuns {
+    fn default() -> Self {
+        TreeRuns {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NIL,
+            seed: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+}
+
+impl fmt::Debug for TreeRuns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl TreeRuns {
+    /// Adds a node without children for the run
+    fn node(&mut self, run: Run) -> usize {
+        // xorshift64, the priorities only need to be spread evenly
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+
+        let node = Node {
+            run,
+            priority: self.seed,
+            left: NIL,
+            right: NIL,
+            count: 1,
+            ones: run.length,
+        };
+
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Releases every node of the subtree
+    fn free(&mut self, node: usize) {
+        let mut next = self.free.len();
+        self.free.push(node);
+
+        // The free list is the queue of the nodes whose children are still to release
+        while let Some(&node) = self.free.get(next) {
+            let Node { left, right, .. } = self.nodes[node];
+            self.free
+                .extend([left, right].into_iter().filter(|child| *child != NIL));
+            next += 1;
+        }
+    }
+
+    fn count_of(&self, node: usize) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node].count
+        }
+    }
+
+    fn ones_of(&self, node: usize) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node].ones
+        }
+    }
+
+    /// Recomputes the counts of a node from the ones of its children
+    fn update(&mut self, node: usize) {
+        let Node {
+            run, left, right, ..
+        } = self.nodes[node];
+
+        self.nodes[node].count = 1 + self.count_of(left) + self.count_of(right);
+        self.nodes[node].ones = run.length + self.ones_of(left) + self.ones_of(right);
+    }
+
+    /// Splits the subtree in the runs starting before `start`, and the other ones
+    fn split(&mut self, node: usize, start: usize) -> (usize, usize) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+
+        if self.nodes[node].run.start < start {
+            let (left, right) = self.split(self.nodes[node].right, start);
+            self.nodes[node].right = left;
+            self.update(node);
+            (node, right)
+        } else {
+            let (left, right) = self.split(self.nodes[node].left, start);
+            self.nodes[node].left = right;
+            self.update(node);
+            (left, node)
+        }
+    }
+
+    /// Joins two subtrees, where every run of `left` starts before the ones of `right`
+    fn merge(&mut self, left: usize, right: usize) -> usize {
+        if left == NIL {
+            return right;
+        }
+        if right == NIL {
+            return left;
+        }
+
+        if self.nodes[left].priority > self.nodes[right].priority {
+            self.nodes[left].right = self.merge(self.nodes[left].right, right);
+            self.update(left);
+            left
+        } else {
+            self.nodes[right].left = self.merge(left, self.nodes[right].left);
+            self.update(right);
+            right
+        }
+    }
+
+    /// Removes the last run of a non-empty subtree, returning the subtree left and the run
+    fn pop_last(&mut self, node: usize) -> (usize, Run) {
+        let Node {
+            run, left, right, ..
+        } = self.nodes[node];
+
+        if right == NIL {
+            self.free.push(node);
+            return (left, run);
+        }
+
+        let (right, last) = self.pop_last(right);
+        self.nodes[node].right = right;
+        self.update(node);
+        (node, last)
+    }
+
+    fn last_of(&self, mut node: usize) -> Option<Run> {
+        while node != NIL && self.nodes[node].right != NIL {
+            node = self.nodes[node].right;
+        }
+
+        (node != NIL).then(|| self.nodes[node].run)
+    }
+}
+
+impl Store for TreeRuns {
+    fn iter(&self) -> impl Iterator<Item = Run> + '_ {
+        let mut pending = Vec::new();
+        let mut node = self.root;
+
+        // In-order traversal, keeping the nodes whose right subtree is still to visit
+        core::iter::from_fn(move || {
+            while node != NIL {
+                pending.push(node);
+                node = self.nodes[node].left;
+            }
+
+            let next = pending.pop()?;
+            node = self.nodes[next].right;
+            Some(self.nodes[next].run)
+        })
+    }
+
+    fn count(&self) -> usize {
+        self.count_of(self.root)
+    }
+
+    fn ones(&self) -> usize {
+        self.ones_of(self.root)
+    }
+
+    fn first(&self) -> Option<Run> {
+        let mut node = self.root;
+
+        while node != NIL && self.nodes[node].left != NIL {
+            node = self.nodes[node].left;
+        }
+
+        (node != NIL).then(|| self.nodes[node].run)
+    }
+
+    fn last(&self) -> Option<Run> {
+        self.last_of(self.root)
+    }
+
+    #[inline(always)]
+    fn floor(&self, position: usize) -> Option<Run> {
+        let mut node = self.root;
+        let mut floor = None;
+
+        while node != NIL {
+            let Node {
+                run, left, right, ..
+            } = self.nodes[node];
+
+            if run.start <= position {
+                floor = Some(run);
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+
+        floor
+    }
+
+    fn insert(&mut self, run: Run) {
+        let (mut before, after) = self.split(self.root, run.start);
+        let mut union = run;
+
+        if self
+            .last_of(before)
+            .is_some_and(|last| last.end() >= run.start)
+        {
+            let last;
+            (before, last) = self.pop_last(before);
+            union = last.union(&union).unwrap_or(union);
+        }
+
+        // Every run starting inside the union, or right after it, is merged into it
+        let (inside, after) = self.split(after, union.end().saturating_add(1));
+        if let Some(last) = self.last_of(inside) {
+            union = union.union(&last).unwrap_or(union);
+            self.free(inside);
+        }
+
+        let node = self.node(union);
+        let before = self.merge(before, node);
+        self.root = self.merge(before, after);
+    }
+
+    fn remove(&mut self, run: Run) {
+        let (mut before, after) = self.split(self.root, run.start);
+        let mut left = None;
+        let mut right = None;
+
+        if self
+            .last_of(before)
+            .is_some_and(|last| last.end() > run.start)
+        {
+            let last;
+            (before, last) = self.pop_last(before);
+            left = Some(Run::from_range((last.start, run.start)));
+            right = Some(Run::from_range((run.end(), last.end().max(run.end()))));
+        }
+
+        // Runs starting inside the removed run are dropped, and only the last one can
+        // continue after it
+        let (inside, after) = self.split(after, run.end());
+        if let Some(last) = self.last_of(inside) {
+            right = Some(Run::from_range((run.end(), last.end().max(run.end()))));
+            self.free(inside);
+        }
+
+        for piece in [left, right].into_iter().flatten() {
+            if piece.length > 0 {
+                let node = self.node(piece);
+                before = self.merge(before, node);
+            }
+        }
+
+        self.root = self.merge(before, after);
+    }
+
+    fn push(&mut self, mut run: Run) {
+        let mut root = self.root;
+
+        if let Some(union) = self.last().and_then(|last| last.union(&run)) {
+            (root, _) = self.pop_last(root);
+            run = union;
+        }
+
+        let node = self.node(run);
+        self.root = self.merge(root, node);
+    }
+
+    fn retain<F: FnMut(&Run) -> bool>(&mut self, mut keep: F) {
+        let runs: Vec<Run> = self.iter().filter(|run| keep(run)).collect();
+
+        *self = TreeRuns::default();
+        for run in runs {
+            self.push(run);
+        }
+    }
+
+    fn truncate(&mut self, size: usize) {
+        if let Some(last) = self.last().filter(|last| last.end() > size) {
+            self.remove(Run::from_range((size, last.end())));
+        }
+    }
+
+    fn rank(&self, position: usize) -> usize {
+        let mut node = self.root;
+        let mut rank = 0;
+
+        // The left subtree of a run starting before the position is entirely before it
+        while node != NIL {
+            let Node {
+                run, left, right, ..
+            } = self.nodes[node];
+
+            if run.start < position {
+                rank += self.ones_of(left) + run.end().min(position) - run.start;
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+
+        rank
+    }
+
+    fn select(&self, mut k: usize) -> Option<usize> {
+        let mut node = self.root;
+
+        while node != NIL {
+            let Node {
+                run, left, right, ..
+            } = self.nodes[node];
+            let before = self.ones_of(left);
+
+            if k < before {
+                node = left;
+            } else if k - before < run.length {
+                return Some(run.start + k - before);
+            } else {
+                k -= before + run.length;
+                node = right;
+            }
+        }
+
+        None
+    }
+}
+
+impl From<&TreeSparseBitmap> for SparseBitmap {
+    fn from(tree: &TreeSparseBitmap) -> Self {
+        let mut sparse = SparseBitmap::new(tree.size);
+
+        for run in tree.runs.iter() {
+            sparse.runs.push(run);
+        }
+
+        sparse
+    }
+}
+
+impl From<&SparseBitmap> for TreeSparseBitmap {
+    fn from(sparse: &SparseBitmap) -> Self {
+        let mut tree = SparseBitmap::with_store(sparse.size, TreeRuns::default());
+
+        for (start, end) in sparse.ranges() {
+            tree.runs.push(Run::from_range((start, end)));
+        }
+
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_string;
+    use alloc::{string::ToString, vec, vec::Vec};
+    use proptest::prelude::*;
+
+    fn tree_sparse(value: &str) -> TreeSparseBitmap {
+        TreeSparseBitmap::from(&SparseBitmap::from(value))
+    }
+
+    fn runs(tree: &TreeSparseBitmap) -> Vec<(usize, usize)> {
+        tree.runs
+            .iter()
+            .map(|run| (run.start, run.length))
+            .collect()
+    }
+
+    #[test]
+    fn test_tree_sparse_set() {
+        let mut tree = SparseBitmap::with_store(10, TreeRuns::default());
+
+        tree.set(2, true);
+        tree.set(4, true);
+        tree.set(3, true);
+        assert_eq!(runs(&tree), vec![(2, 3)]);
+
+        tree.set(3, false);
+        assert_eq!(runs(&tree), vec![(2, 1), (4, 1)]);
+
+        tree.set(2, false);
+        assert_eq!(runs(&tree), vec![(4, 1)]);
+        assert!(tree.get(4) && !tree.get(3));
+        assert_eq!(tree.to_string(), "0000010000");
+    }
+
+    #[test]
+    fn test_tree_sparse_ranges() {
+        let mut tree = tree_sparse("0011001100110000");
+        assert_eq!(runs(&tree), vec![(4, 2), (8, 2), (12, 2)]);
+
+        // Merges the run it overlaps, and the one touching its end
+        tree.insert_range(3..8);
+        assert_eq!(runs(&tree), vec![(3, 7), (12, 2)]);
+
+        // Splits the run it falls into
+        tree.remove_range(4..6);
+        assert_eq!(runs(&tree), vec![(3, 1), (6, 4), (12, 2)]);
+
+        // Shortens the runs at both ends of the range
+        tree.remove_range(7..13);
+        assert_eq!(runs(&tree), vec![(3, 1), (6, 1), (13, 1)]);
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn test_tree_sparse_counts_subtrees() {
+        let mut tree = SparseBitmap::with_store(100_000, TreeRuns::default());
+        for position in (0..100_000).step_by(3) {
+            tree.set(position, true);
+        }
+        tree.remove_range(30_000..60_000);
+
+        assert_eq!(tree.len(), 23_334);
+        assert_eq!(tree.rank(60_000), 10_000);
+        assert_eq!(tree.select(10_000), Some(60_000));
+        assert_eq!(tree.nth(23_334), None);
+        assert_eq!(tree.runs.nodes.len() - tree.runs.free.len(), 23_334);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_tree_sparse_range_out_of_bounds() {
+        SparseBitmap::with_store(5, TreeRuns::default()).insert_range(3..6);
+    }
+
+    fn bits() -> impl Strategy<Value = Vec<bool>> {
+        prop::collection::vec(prop::bool::weighted(0.4), 0..200)
+    }
+
+    proptest! {
+        #[test]
+        fn test_tree_sparse_matches_sparse(
+            bits in bits(),
+            operations in prop::collection::vec((any::<usize>(), 0..20usize, any::<bool>()), 0..40),
+            rhs_bits in bits(),
+        ) {
+            let (string, rhs_string) = (to_string(&bits), to_string(&rhs_bits));
+
+            let mut tree = tree_sparse(&string);
+            let mut sparse = SparseBitmap::from(string.as_str());
+
+            for (start, length, value) in operations {
+                if bits.is_empty() {
+                    break;
+                }
+
+                let start = start % bits.len();
+                let end = (start + length).min(bits.len());
+                prop_assert_eq!(tree.contains_range(start..end), sparse.contains_range(start..end));
+                prop_assert_eq!(tree.intersects_range(start..end), sparse.intersects_range(start..end));
+
+                match value {
+                    true => tree.insert_range(start..end),
+                    false => tree.remove_range(start..end),
+                }
+                for position in start..end {
+                    sparse.set(position, value);
+                }
+
+                prop_assert_eq!(SparseBitmap::from(&tree), sparse.clone());
+                prop_assert_eq!(tree.validate(), Ok(()));
+            }
+
+            let rhs_tree = tree_sparse(&rhs_string);
+            let rhs = SparseBitmap::from(rhs_string.as_str());
+
+            prop_assert_eq!(SparseBitmap::from(&(&tree & &rhs_tree)), &sparse & &rhs);
+            prop_assert_eq!(SparseBitmap::from(&(&tree | &rhs_tree)), &sparse | &rhs);
+            prop_assert_eq!(SparseBitmap::from(&(&tree ^ &rhs_tree)), &sparse ^ &rhs);
+            prop_assert_eq!(SparseBitmap::from(&!&tree), !&sparse);
+            prop_assert_eq!((&tree | &rhs_tree).validate(), Ok(()));
+            prop_assert_eq!((&tree ^ &rhs_tree).validate(), Ok(()));
+            prop_assert_eq!(tree.to_string(), sparse.to_string());
+            prop_assert_eq!(tree.len(), sparse.len());
+            prop_assert_eq!(tree.runs().collect::<Vec<_>>(), sparse.runs().collect::<Vec<_>>());
+            prop_assert_eq!((tree.first(), tree.last()), (sparse.first(), sparse.last()));
+            for position in 0..=sparse.size {
+                prop_assert_eq!(tree.rank(position), sparse.rank(position));
+            }
+            for k in 0..=sparse.len() {
+                prop_assert_eq!(tree.select(k), sparse.select(k));
+            }
+        }
+    }
+}